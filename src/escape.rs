@@ -0,0 +1,153 @@
+/// Schemes of URLs, which may be rendered as links (URLs without scheme are relative and therefore also fine)
+const ALLOWED_URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Escape text for HTML element content
+pub fn html_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape text for (quoted) HTML attribute values
+pub fn html_attribute(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&#39;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encode text for a component of an URL (e.g. a path segment), only unreserved characters are kept
+pub fn url_component(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                escaped.push(byte as char)
+            }
+            _ => escaped += &format!("%{:02X}", byte),
+        }
+    }
+    escaped
+}
+
+/// The URL, if its scheme is allowed (e.g. no "javascript:" or "data:")
+pub fn safe_url(url: &str) -> Option<&str> {
+    // Browsers ignore whitespace and control characters within the scheme, so do the same
+    let cleaned: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    let scheme_end = cleaned.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(end) if cleaned[end..].starts_with(':') => {
+            let scheme = cleaned[..end].to_lowercase();
+            if ALLOWED_URL_SCHEMES.contains(&scheme.as_str()) {
+                Some(url.trim())
+            } else {
+                None
+            }
+        }
+        _ => Some(url.trim()), // relative
+    }
+}
+
+/// The URL, if it is also absolute with a web scheme (for clients, which reject e.g. relative links)
+pub fn absolute_url(url: &str) -> Option<&str> {
+    let url = safe_url(url)?;
+    let scheme = url.split_once("://")?.0.to_lowercase();
+    matches!(scheme.as_str(), "http" | "https").then_some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_text_escapes_tags() {
+        assert_eq!(
+            html_text("<script>alert('x' & \"y\")</script>"),
+            "&lt;script&gt;alert('x' &amp; \"y\")&lt;/script&gt;"
+        );
+    }
+
+    #[test]
+    fn html_attribute_escapes_quotes() {
+        assert_eq!(
+            html_attribute("https://e.com/?q=\"><script>x</script>&a='b'"),
+            "https://e.com/?q=&quot;&gt;&lt;script&gt;x&lt;/script&gt;&amp;a=&#39;b&#39;"
+        );
+    }
+
+    #[test]
+    fn url_component_encodes_reserved_characters() {
+        assert_eq!(
+            url_component("a b/ä?x=1&y#"),
+            "a%20b%2F%C3%A4%3Fx%3D1%26y%23"
+        );
+    }
+
+    #[test]
+    fn unsafe_urls_are_dropped() {
+        for url in [
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            "java\tscript:alert(1)",
+            " javascript:alert(1)",
+            "java\nscript:alert(1)",
+            "java\u{0}script:alert(1)",
+            "data:text/html;base64,PHNjcmlwdD4=",
+            "vbscript:msgbox(1)",
+        ] {
+            assert_eq!(safe_url(url), None, "{:?}", url);
+        }
+    }
+
+    #[test]
+    fn safe_and_relative_urls_are_kept() {
+        for url in [
+            "https://grafana.example.com/d/abc?from=now-1h",
+            "HTTP://example.com",
+            "mailto:oncall@example.com",
+            "/d/abc?orgId=1",
+            "d/abc",
+            "?tab=alerts",
+            "#panel-2",
+            "./javascript:alert(1)", // relative path, not a scheme
+        ] {
+            assert_eq!(safe_url(url), Some(url), "{:?}", url);
+        }
+    }
+
+    #[test]
+    fn only_absolute_web_urls_are_absolute() {
+        for url in ["https://grafana.example.com/d/abc", "HTTP://example.com"] {
+            assert_eq!(absolute_url(url), Some(url), "{:?}", url);
+        }
+        for url in [
+            "/d/abc?orgId=1",
+            "d/abc",
+            "//example.com/x",
+            "mailto:oncall@example.com",
+            "javascript://%0aalert(1)",
+            "ht tp://example.com",
+            "ftp://example.com",
+        ] {
+            assert_eq!(absolute_url(url), None, "{:?}", url);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use log::debug;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+/// Configuration of the outbound HTTP client used to deliver messages to a sink
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct HttpClientConfig {
+    timeout: Option<u64>,                     // in seconds (default: 10)
+    proxy: Option<String>,                    // used for both HTTP and HTTPS
+    ca_bundle: Option<String>,                // PEM file with additional trusted CAs
+    client_certificate: Option<String>,       // PEM file with certificate and key (mTLS)
+    verify_tls: Option<bool>,                 // (default: true) for lab setups only!
+    headers: Option<HashMap<String, String>>, // static headers added to every request
+    user_agent: Option<String>,               // (default: "rust-webhook-transformer/<version>")
+    #[serde(skip)]
+    client: Arc<OnceLock<Result<reqwest::Client, String>>>, // shared by all clones of this config
+    #[serde(skip)]
+    client_without_redirects: Arc<OnceLock<Result<reqwest::Client, String>>>,
+}
+
+impl std::fmt::Debug for HttpClientConfig {
+    /// The values of the headers are redacted, as they often contain secrets (e.g. "X-Api-Key")
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headers: Option<Vec<&String>> = self.headers.as_ref().map(|h| h.keys().collect());
+        f.debug_struct("HttpClientConfig")
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .field("ca_bundle", &self.ca_bundle)
+            .field("client_certificate", &self.client_certificate)
+            .field("verify_tls", &self.verify_tls)
+            .field("headers", &headers)
+            .field("user_agent", &self.user_agent)
+            .finish()
+    }
+}
+
+impl HttpClientConfig {
+    /// Get the (lazily built) client of this configuration, connections are pooled across calls
+    pub fn client(&self) -> Result<reqwest::Client, String> {
+        self.client.get_or_init(|| self.build(true)).clone()
+    }
+
+    /// Get a client, which does not follow redirects (e.g. to check each target before requesting it)
+    pub fn client_without_redirects(&self) -> Result<reqwest::Client, String> {
+        self.client_without_redirects
+            .get_or_init(|| self.build(false))
+            .clone()
+    }
+
+    fn build(&self, follow_redirects: bool) -> Result<reqwest::Client, String> {
+        debug!("Building HTTP client: {:#?}", self);
+        let mut builder = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(self.timeout.unwrap_or(10)))
+            .user_agent(self.user_agent.clone().unwrap_or(format!(
+                "{}/{}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            )));
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| "Failed to parse the proxy: ".to_string() + &e.to_string())?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(ca_bundle) = &self.ca_bundle {
+            let pem = std::fs::read(ca_bundle)
+                .map_err(|e| "Failed to read the CA bundle: ".to_string() + &e.to_string())?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| "Failed to parse the CA bundle: ".to_string() + &e.to_string())?;
+            builder = builder.tls_certs_merge(certificates);
+        }
+        if let Some(client_certificate) = &self.client_certificate {
+            let pem = std::fs::read(client_certificate).map_err(|e| {
+                "Failed to read the client certificate: ".to_string() + &e.to_string()
+            })?;
+            let identity = reqwest::Identity::from_pem(&pem).map_err(|e| {
+                "Failed to parse the client certificate: ".to_string() + &e.to_string()
+            })?;
+            builder = builder.identity(identity);
+        }
+        if !follow_redirects {
+            builder = builder.redirect(reqwest::redirect::Policy::none());
+        }
+        if !self.verify_tls.unwrap_or(true) {
+            builder = builder.tls_danger_accept_invalid_certs(true);
+        }
+        if let Some(headers) = &self.headers {
+            let mut header_map = HeaderMap::new();
+            for (name, value) in headers {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|e| format!("Invalid header name {}: {}", name, e))?;
+                let value = HeaderValue::from_str(value)
+                    .map_err(|e| format!("Invalid value for header {}: {}", name, e))?;
+                header_map.insert(name, value);
+            }
+            builder = builder.default_headers(header_map);
+        }
+        builder
+            .build()
+            .map_err(|e| "Failed to build the HTTP client: ".to_string() + &e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(value: serde_json::Value) -> HttpClientConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn headers_are_sent_with_every_request() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let length = stream.read(&mut request).unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n");
+            String::from_utf8_lossy(&request[..length]).to_lowercase()
+        });
+
+        let http = config(serde_json::json!({
+            "headers": { "X-Api-Key": "s3cret" },
+            "user_agent": "alerts/1.0",
+        }));
+        let client = http.client().unwrap();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/", port))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 204);
+        let request = server.join().unwrap();
+        assert!(request.contains("x-api-key: s3cret\r\n"), "{}", request);
+        assert!(
+            request.contains("user-agent: alerts/1.0\r\n"),
+            "{}",
+            request
+        );
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let error = config(serde_json::json!({ "headers": { "X Api Key": "s3cret" } }))
+            .client()
+            .unwrap_err();
+        assert!(
+            error.starts_with("Invalid header name X Api Key"),
+            "{}",
+            error
+        );
+        let error = config(serde_json::json!({ "headers": { "X-Api-Key": "a\nb" } }))
+            .client()
+            .unwrap_err();
+        assert!(
+            error.starts_with("Invalid value for header x-api-key"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn invalid_proxies_are_rejected() {
+        let error = config(serde_json::json!({ "proxy": "http://[proxy" }))
+            .client()
+            .unwrap_err();
+        assert!(error.starts_with("Failed to parse the proxy"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_ca_bundles_are_rejected() {
+        let missing = std::env::temp_dir().join("rust-webhook-transformer-missing.pem");
+        let error = config(serde_json::json!({ "ca_bundle": missing }))
+            .client()
+            .unwrap_err();
+        assert!(
+            error.starts_with("Failed to read the CA bundle"),
+            "{}",
+            error
+        );
+
+        let invalid = std::env::temp_dir().join(format!(
+            "rust-webhook-transformer-{}.pem",
+            std::process::id()
+        ));
+        std::fs::write(
+            &invalid,
+            "-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+        let result = config(serde_json::json!({ "ca_bundle": invalid })).client();
+        std::fs::remove_file(&invalid).unwrap();
+        let error = result.unwrap_err();
+        assert!(
+            error.starts_with("Failed to parse the CA bundle"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn header_values_are_redacted() {
+        let http = config(serde_json::json!({
+            "proxy": "http://proxy.example.com:3128",
+            "headers": { "X-Api-Key": "s3cret" },
+        }));
+        let debug = format!("{:?}", http);
+        assert!(debug.contains("X-Api-Key"), "{}", debug);
+        assert!(debug.contains("proxy.example.com"), "{}", debug);
+        assert!(!debug.contains("s3cret"), "{}", debug);
+    }
+}
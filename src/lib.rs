@@ -1 +1,8 @@
+pub mod escape;
+pub mod http;
+pub mod locale;
+pub mod message;
+pub mod sink;
+pub mod template;
+pub mod time;
 pub mod transformer;
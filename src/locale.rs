@@ -0,0 +1,361 @@
+use crate::message::Span;
+use crate::template;
+use serde::{Deserialize, Serialize};
+
+/// Language of the generated wording, selected per transformer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    En,
+    De,
+}
+
+// The phrases are templates, plural forms are separated by "|" and chosen by the "count" argument
+const ENGLISH: &[(&str, &str)] = &[
+    // Grafana
+    (
+        "grafana.firing",
+        "🚨 {{count}} alert is firing{{others}}|🚨 {{count}} alerts are firing{{others}}",
+    ),
+    (
+        "grafana.pending",
+        "⚠️ {{count}} alert is pending{{others}}...|⚠️ {{count}} alerts are pending{{others}}...",
+    ),
+    ("grafana.others", " ({{list}})"),
+    ("grafana.others.and", " and "),
+    ("grafana.resolved", "✅ All alerts are resolved!"),
+    ("grafana.instance", " at "),
+    (
+        "grafana.since.firing",
+        "Firing since {{since}} ({{duration}})",
+    ),
+    (
+        "grafana.since.pending",
+        "Pending since {{since}} ({{duration}})",
+    ),
+    (
+        "grafana.since.resolved",
+        "Resolved at {{until}} after {{duration}}",
+    ),
+    (
+        "grafana.since.resolved.started",
+        "Resolved, started at {{since}}",
+    ),
+    ("grafana.group.without", "Without {{label}}"),
+    ("grafana.count.firing", "{{count}} firing"),
+    ("grafana.count.pending", "{{count}} pending"),
+    ("grafana.count.resolved", "{{count}} resolved"),
+    ("link.dashboard", "dashboard"),
+    ("link.panel", "panel"),
+    ("link.silence", "silence"),
+    ("link.image", "image"),
+    ("link.runbook", "runbook"),
+    ("link.rule", "rule"),
+    ("link.alert_group", "alert group"),
+    // Grafana OnCall
+    ("oncall.state", " is {{state}}"),
+    ("oncall.state.firing", "firing"),
+    ("oncall.state.new", "new"),
+    ("oncall.state.acknowledged", "acknowledged"),
+    ("oncall.state.resolved", "resolved"),
+    ("oncall.state.silenced", "silenced"),
+    ("oncall.event.escalation", "Escalated"),
+    ("oncall.event.acknowledge", "Acknowledged"),
+    ("oncall.event.unacknowledge", "Unacknowledged"),
+    ("oncall.event.resolve", "Resolved"),
+    ("oncall.event.unresolve", "Unresolved"),
+    ("oncall.event.silence", "Silenced"),
+    ("oncall.event.unsilence", "Unsilenced"),
+    ("oncall.event.other", "Event {{event}}"),
+    ("oncall.by", " by "),
+    ("oncall.alerts", " ({{count}} alert)| ({{count}} alerts)"),
+    ("oncall.title", "Alert group"),
+    // UptimeKuma
+    ("uptime_kuma.since.down", "Down since {{since}}"),
+    ("uptime_kuma.since.up", "Up since {{since}}"),
+    ("uptime_kuma.since.other", "Changed since {{since}}"),
+    // Gitlab
+    (
+        "gitlab.push",
+        "{{user}} pushed {{count}} commit to {{project}}|{{user}} pushed {{count}} commits to {{project}}",
+    ),
+    ("gitlab.tag_push", "{{user}} pushed a tag to {{project}}"),
+    (
+        "gitlab.pipeline",
+        "Pipeline {{pipeline}} {{status}} for {{project}}",
+    ),
+    (
+        "gitlab.repository_update",
+        "The repository {{project}} was updated by {{user}}",
+    ),
+    // Sinks
+    ("limit.more", "… and {{count}} more"),
+    ("limit.continued", " (continued)"),
+];
+
+const GERMAN: &[(&str, &str)] = &[
+    // Grafana
+    (
+        "grafana.firing",
+        "🚨 {{count}} Alarm wurde ausgelöst{{others}}|🚨 {{count}} Alarme wurden ausgelöst{{others}}",
+    ),
+    (
+        "grafana.pending",
+        "⚠️ {{count}} Alarm steht aus{{others}}...|⚠️ {{count}} Alarme stehen aus{{others}}...",
+    ),
+    ("grafana.others", " ({{list}})"),
+    ("grafana.others.and", " und "),
+    ("grafana.resolved", "✅ Alle Alarme sind behoben!"),
+    ("grafana.instance", " auf "),
+    (
+        "grafana.since.firing",
+        "Ausgelöst seit {{since}} ({{duration}})",
+    ),
+    (
+        "grafana.since.pending",
+        "Ausstehend seit {{since}} ({{duration}})",
+    ),
+    (
+        "grafana.since.resolved",
+        "Behoben um {{until}} nach {{duration}}",
+    ),
+    (
+        "grafana.since.resolved.started",
+        "Behoben, begonnen um {{since}}",
+    ),
+    ("grafana.group.without", "Ohne {{label}}"),
+    ("grafana.count.firing", "{{count}} ausgelöst"),
+    ("grafana.count.pending", "{{count}} ausstehend"),
+    ("grafana.count.resolved", "{{count}} behoben"),
+    ("link.dashboard", "Dashboard"),
+    ("link.panel", "Panel"),
+    ("link.silence", "Stummschalten"),
+    ("link.image", "Bild"),
+    ("link.runbook", "Runbook"),
+    ("link.rule", "Regel"),
+    ("link.alert_group", "Alarmgruppe"),
+    // Grafana OnCall
+    ("oncall.state", " ist {{state}}"),
+    ("oncall.state.firing", "ausgelöst"),
+    ("oncall.state.new", "neu"),
+    ("oncall.state.acknowledged", "bestätigt"),
+    ("oncall.state.resolved", "behoben"),
+    ("oncall.state.silenced", "stummgeschaltet"),
+    ("oncall.event.escalation", "Eskaliert"),
+    ("oncall.event.acknowledge", "Bestätigt"),
+    ("oncall.event.unacknowledge", "Bestätigung aufgehoben"),
+    ("oncall.event.resolve", "Behoben"),
+    ("oncall.event.unresolve", "Wieder geöffnet"),
+    ("oncall.event.silence", "Stummgeschaltet"),
+    ("oncall.event.unsilence", "Stummschaltung aufgehoben"),
+    ("oncall.event.other", "Ereignis {{event}}"),
+    ("oncall.by", " von "),
+    ("oncall.alerts", " ({{count}} Alarm)| ({{count}} Alarme)"),
+    ("oncall.title", "Alarmgruppe"),
+    // UptimeKuma
+    ("uptime_kuma.since.down", "Nicht erreichbar seit {{since}}"),
+    ("uptime_kuma.since.up", "Erreichbar seit {{since}}"),
+    ("uptime_kuma.since.other", "Geändert seit {{since}}"),
+    // Gitlab
+    (
+        "gitlab.push",
+        "{{user}} hat {{count}} Commit nach {{project}} gepusht|{{user}} hat {{count}} Commits nach {{project}} gepusht",
+    ),
+    (
+        "gitlab.tag_push",
+        "{{user}} hat ein Tag nach {{project}} gepusht",
+    ),
+    (
+        "gitlab.pipeline",
+        "Pipeline {{pipeline}} für {{project}}: {{status}}",
+    ),
+    (
+        "gitlab.repository_update",
+        "Das Repository {{project}} wurde von {{user}} aktualisiert",
+    ),
+    // Sinks
+    ("limit.more", "… und {{count}} weitere"),
+    ("limit.continued", " (Fortsetzung)"),
+];
+
+impl Language {
+    /// Translate the phrase (falling back to English), the arguments replace its placeholders
+    pub fn text(self, key: &str, args: &serde_json::Value) -> String {
+        template::render(self.phrase(key, args), args)
+    }
+
+    /// Translate the phrase into spans, the placeholders named in `spans` are replaced by them (e.g. links)
+    pub fn spans(self, key: &str, args: &serde_json::Value, spans: &[(&str, Span)]) -> Vec<Span> {
+        let mut result = Vec::new();
+        let mut rest = self.phrase(key, args);
+        loop {
+            let next = spans
+                .iter()
+                .filter_map(|(name, span)| {
+                    let placeholder = format!("{{{{{}}}}}", name);
+                    Some((rest.find(&placeholder)?, placeholder.len(), span))
+                })
+                .min_by_key(|(position, _, _)| *position);
+            let Some((position, length, span)) = next else {
+                break;
+            };
+            if position > 0 {
+                result.push(Span::plain(&template::render(&rest[..position], args)));
+            }
+            result.push(span.clone());
+            rest = &rest[position + length..];
+        }
+        if !rest.is_empty() {
+            result.push(Span::plain(&template::render(rest, args)));
+        }
+        result
+    }
+
+    /// Find the phrase in the catalog of the language and choose its plural form
+    fn phrase<'a>(self, key: &'a str, args: &serde_json::Value) -> &'a str {
+        let catalog = match self {
+            Language::En => ENGLISH,
+            Language::De => GERMAN,
+        };
+        phrase(catalog, key, args)
+    }
+}
+
+/// Find the phrase in the catalog (falling back to English, or just the key) and choose its plural form
+fn phrase<'a>(catalog: &[(&str, &'a str)], key: &'a str, args: &serde_json::Value) -> &'a str {
+    let phrase = catalog
+        .iter()
+        .chain(ENGLISH)
+        .find(|(candidate, _)| *candidate == key)
+        .map(|(_, phrase)| *phrase)
+        .unwrap_or(key);
+    let forms: Vec<&str> = phrase.split('|').collect();
+    // English and German only distinguish between one and other (including zero)
+    let index = match args.get("count").and_then(|count| count.as_u64()) {
+        Some(1) | None => 0,
+        Some(_) => 1,
+    };
+    forms[index.min(forms.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Style, spans_to_text};
+    use serde_json::json;
+
+    #[test]
+    fn plural_forms_are_chosen_by_the_count() {
+        for (language, count, expected) in [
+            (Language::En, 0, "0 firing"),
+            (Language::En, 1, "1 firing"),
+            (Language::En, 2, "2 firing"),
+            (Language::De, 0, "0 ausgelöst"),
+        ] {
+            let text = language.text("grafana.count.firing", &json!({ "count": count }));
+            assert_eq!(text, expected);
+        }
+        for (count, expected) in [(0, " (0 alerts)"), (1, " (1 alert)"), (7, " (7 alerts)")] {
+            let text = Language::En.text("oncall.alerts", &json!({ "count": count }));
+            assert_eq!(text, expected);
+        }
+        let german = |count: u64| Language::De.text("oncall.alerts", &json!({ "count": count }));
+        assert_eq!(german(0), " (0 Alarme)");
+        assert_eq!(german(1), " (1 Alarm)");
+        assert_eq!(german(3), " (3 Alarme)");
+        // Without a count, the first form is used
+        assert_eq!(
+            phrase(ENGLISH, "oncall.alerts", &json!({})),
+            " ({{count}} alert)"
+        );
+    }
+
+    #[test]
+    fn missing_phrases_fall_back_to_english() {
+        let partial: &[(&str, &str)] = &[("link.image", "Bild")];
+        assert_eq!(phrase(partial, "link.image", &json!({})), "Bild");
+        assert_eq!(phrase(partial, "link.runbook", &json!({})), "runbook");
+        assert_eq!(phrase(partial, "no.such.key", &json!({})), "no.such.key");
+        assert_eq!(Language::De.text("no.such.key", &json!({})), "no.such.key");
+    }
+
+    #[test]
+    fn placeholders_are_replaced_by_spans() {
+        let spans = Language::En.spans(
+            "gitlab.pipeline",
+            &json!({ "status": "failed" }),
+            &[
+                (
+                    "pipeline",
+                    Span::plain("#42").link("https://gitlab.example.com/p/42"),
+                ),
+                ("project", Span::bold("app")),
+            ],
+        );
+        let texts: Vec<&str> = spans.iter().map(|span| span.text.as_str()).collect();
+        assert_eq!(
+            spans_to_text(&spans),
+            Language::En.text(
+                "gitlab.pipeline",
+                &json!({ "status": "failed", "pipeline": "#42", "project": "app" }),
+            )
+        );
+        let pipeline = texts.iter().position(|text| *text == "#42").unwrap();
+        let project = texts.iter().position(|text| *text == "app").unwrap();
+        assert_eq!(
+            spans[pipeline].url.as_deref(),
+            Some("https://gitlab.example.com/p/42")
+        );
+        assert_eq!(spans[project].style, Style::Bold);
+        // Only the named placeholders become spans, the others are rendered as text
+        assert!(spans.iter().all(|span| !span.text.contains("{{")));
+        assert!(spans_to_text(&spans).contains("failed"));
+
+        // A span at the very start and end, and repeated placeholders
+        let template: &[(&str, Span)] = &[("project", Span::bold("app"))];
+        let spans = Language::En.spans("{{project}} and {{project}}", &json!({}), template);
+        let texts: Vec<&str> = spans.iter().map(|span| span.text.as_str()).collect();
+        assert_eq!(texts, vec!["app", " and ", "app"]);
+    }
+
+    #[test]
+    fn catalogs_do_not_drift_apart() {
+        let placeholders = |phrase: &str| {
+            let mut names: Vec<String> = phrase
+                .split("{{")
+                .skip(1)
+                .filter_map(|part| part.split_once("}}").map(|(name, _)| name.to_string()))
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        };
+        for (key, german) in GERMAN {
+            let english = ENGLISH
+                .iter()
+                .find(|(candidate, _)| candidate == key)
+                .map(|(_, phrase)| *phrase)
+                .unwrap_or_else(|| panic!("{} is missing in English", key));
+            assert_eq!(
+                german.split('|').count(),
+                english.split('|').count(),
+                "plural forms of {}",
+                key
+            );
+            assert_eq!(
+                placeholders(german),
+                placeholders(english),
+                "placeholders of {}",
+                key
+            );
+        }
+        for (key, _) in ENGLISH {
+            assert_eq!(
+                ENGLISH.iter().filter(|(other, _)| other == key).count(),
+                1,
+                "{}",
+                key
+            );
+        }
+    }
+}
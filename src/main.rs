@@ -62,17 +62,15 @@ async fn forward_to_transformers(
                     }
                 }
             }
-            if err.len() == 0 {
-                return HttpResponse::Ok().body("OK");
+            if err.is_empty() {
+                HttpResponse::Ok().body("OK")
             } else {
-                return HttpResponse::InternalServerError()
-                    .body("Internal server error: ".to_string() + &err.to_string());
+                HttpResponse::InternalServerError()
+                    .body("Internal server error: ".to_string() + &err.to_string())
                 // at least one transformer failed
             }
         }
-        None => {
-            return HttpResponse::NotFound().body("Unknown endpoint id");
-        }
+        None => HttpResponse::NotFound().body("Unknown endpoint id"),
     }
 }
 
@@ -88,6 +86,13 @@ async fn main() -> std::io::Result<()> {
             .as_str(),
     )
     .expect("Failed to parse the config file");
+    for (id, transformers) in &config.transformers {
+        for transformer in transformers {
+            if let Err(e) = transformer.validate() {
+                panic!("Invalid configuration of transformer {}: {}", id, e);
+            }
+        }
+    }
 
     HttpServer::new(move || {
         let logger = Logger::default();
@@ -0,0 +1,575 @@
+use crate::escape;
+use crate::locale::Language;
+use actix_web::HttpRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A message, as produced by the transformers and rendered by the sinks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub status: Status,       // overall status of the message
+    pub title: Vec<Span>,     // may be empty, e.g. if the message only consists of one entry
+    pub entries: Vec<Entry>,  // the individual alerts, commits, ...
+    pub body: Option<String>, // preformatted text (Markdown), replaces the title and entries
+    pub url: Option<String>, // e.g. of the dashboard or the compared commits, linked if entries are omitted
+    #[serde(skip)]
+    pub language: Language, // of the generated wording, also used by the sinks
+    #[serde(skip)]
+    pub origin: Option<Origin>, // set by the transformer before submitting the message
+}
+
+/// The incoming request, from which a message was transformed
+#[derive(Debug, Clone)]
+pub struct Origin {
+    pub body: String,
+    pub headers: Vec<(String, String)>, // names are lowercase
+}
+
+/// A single alert, commit, ... contained in a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub status: Status,
+    pub fingerprint: Option<String>, // stable identifier, to correlate e.g. firing and resolved alerts
+    pub headline: Vec<Span>,
+    pub details: Vec<Vec<Span>>, // further lines
+    pub links: Vec<Link>,        // rendered as actions, e.g. "→ dashboard, silence"
+    pub labels: BTreeMap<String, String>,
+    pub author: Option<String>, // e.g. of a commit
+    pub image: Option<String>,  // e.g. a screenshot of the panel of an alert
+    pub group: Option<String>, // heading of consecutive entries, e.g. "severity=critical: 2 firing"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Status {
+    Firing,   // something is wrong, e.g. alert firing, monitor down, pipeline failed
+    Pending,  // something may become wrong or is still in progress
+    Resolved, // everything is fine (again)
+    Unknown,  // the source has a status, but we could not determine it
+    Info,     // the source has no status at all, it is just informational
+}
+
+/// A piece of text with uniform formatting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+    pub url: Option<String>, // if set, the span is a link
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Style {
+    Plain,
+    Bold,
+    Code,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Link {
+    pub label: String,
+    pub url: String,
+}
+
+impl Status {
+    /// Icon shown in front of entries, informational entries have none
+    pub fn icon(&self) -> Option<&'static str> {
+        match self {
+            Status::Firing => Some("🔴"),
+            Status::Pending => Some("🟡"),
+            Status::Resolved => Some("🟢"),
+            Status::Unknown => Some("⚪"),
+            Status::Info => None,
+        }
+    }
+
+    /// Color (as hex RGB) for sinks supporting colored messages
+    pub fn color(&self) -> &'static str {
+        match self {
+            Status::Firing => "#e01e5a",
+            Status::Pending => "#ecb22e",
+            Status::Resolved => "#2eb67d",
+            Status::Unknown => "#9e9e9e",
+            Status::Info => "#1d9bd1",
+        }
+    }
+
+    /// Notification type (as named by Apprise), for receivers without the notion of resolved alerts
+    pub fn level(&self) -> &'static str {
+        match self {
+            Status::Firing => "failure",
+            Status::Pending => "warning",
+            Status::Resolved => "success",
+            Status::Unknown | Status::Info => "info",
+        }
+    }
+}
+
+impl Span {
+    pub fn plain(text: &str) -> Self {
+        Span {
+            text: text.to_string(),
+            style: Style::Plain,
+            url: None,
+        }
+    }
+
+    pub fn bold(text: &str) -> Self {
+        Span {
+            style: Style::Bold,
+            ..Span::plain(text)
+        }
+    }
+
+    pub fn code(text: &str) -> Self {
+        Span {
+            style: Style::Code,
+            ..Span::plain(text)
+        }
+    }
+
+    /// Turn the span into a link
+    pub fn link(self, url: &str) -> Self {
+        Span {
+            url: Some(url.to_string()),
+            ..self
+        }
+    }
+}
+
+/// Concatenate the spans without any formatting
+pub fn spans_to_text(spans: &[Span]) -> String {
+    spans.iter().map(|span| span.text.as_str()).collect()
+}
+
+/// Render the spans as HTML (escaped, links only with allowed schemes)
+pub fn spans_to_html(spans: &[Span]) -> String {
+    let mut html = String::new();
+    for span in spans {
+        let text = escape::html_text(&span.text);
+        let mut span_html = match span.style {
+            Style::Plain => text,
+            Style::Bold => format!("<b>{}</b>", text),
+            Style::Code => format!("<code>{}</code>", text),
+        };
+        if let Some(url) = span.url.as_deref().and_then(escape::safe_url) {
+            span_html = format!(
+                "<a href=\"{}\">{}</a>",
+                escape::html_attribute(url),
+                span_html
+            );
+        }
+        html += &span_html;
+    }
+    html
+}
+
+/// Render the spans as Markdown
+pub fn spans_to_markdown(spans: &[Span]) -> String {
+    let mut markdown = String::new();
+    for span in spans {
+        let mut span_markdown = match span.style {
+            Style::Plain => escape_markdown(&span.text),
+            Style::Bold => format!("**{}**", escape_markdown(&span.text)),
+            Style::Code => format!("`{}`", span.text.replace('`', "'")), // no escaping within code
+        };
+        if let Some(url) = span.url.as_deref().and_then(escape::safe_url) {
+            span_markdown = format!("[{}]({})", span_markdown, url.replace(')', "%29"));
+        }
+        markdown += &span_markdown;
+    }
+    markdown
+}
+
+/// Escape the characters, which have a meaning in Markdown
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '~' | '`' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl Entry {
+    /// Create an entry, which consists only of a headline
+    pub fn new(status: Status, headline: Vec<Span>) -> Self {
+        Entry {
+            status,
+            fingerprint: None,
+            headline,
+            details: Vec::new(),
+            links: Vec::new(),
+            labels: BTreeMap::new(),
+            author: None,
+            image: None,
+            group: None,
+        }
+    }
+
+    /// The headline, prefixed with the status icon (if any)
+    pub fn headline_with_icon(&self) -> Vec<Span> {
+        match self.status.icon() {
+            Some(icon) => {
+                let mut headline = vec![Span::plain(&format!("{} ", icon))];
+                headline.extend(self.headline.iter().cloned());
+                headline
+            }
+            None => self.headline.clone(),
+        }
+    }
+
+    /// Render the entry as HTML lines (headline, details and actions)
+    fn html_lines(&self) -> Vec<String> {
+        let mut lines = vec![spans_to_html(&self.headline_with_icon())];
+        for detail in &self.details {
+            lines.push(spans_to_html(detail));
+        }
+        if !self.links.is_empty() {
+            let links: Vec<String> = self
+                .links
+                .iter()
+                .filter_map(|link| {
+                    let url = escape::safe_url(&link.url)?;
+                    Some(format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape::html_attribute(url),
+                        escape::html_text(&link.label)
+                    ))
+                })
+                .collect();
+            lines.push("→ ".to_string() + &links.join(", "));
+        }
+        lines
+    }
+
+    /// Render the entry as Markdown lines (headline, details and actions)
+    pub fn markdown_lines(&self) -> Vec<String> {
+        let mut lines = vec![spans_to_markdown(&self.headline_with_icon())];
+        for detail in &self.details {
+            lines.push(spans_to_markdown(detail));
+        }
+        if !self.links.is_empty() {
+            let links: Vec<String> = self
+                .links
+                .iter()
+                .filter_map(|link| {
+                    let url = escape::safe_url(&link.url)?;
+                    Some(format!(
+                        "[{}]({})",
+                        escape_markdown(&link.label),
+                        url.replace(')', "%29")
+                    ))
+                })
+                .collect();
+            lines.push("→ ".to_string() + &links.join(", "));
+        }
+        lines
+    }
+
+    /// Render the entry as plain text lines (headline, details and actions)
+    fn text_lines(&self) -> Vec<String> {
+        let mut lines = vec![spans_to_text(&self.headline_with_icon())];
+        for detail in &self.details {
+            lines.push(spans_to_text(detail));
+        }
+        for link in &self.links {
+            lines.push(format!("→ {}: {}", link.label, link.url));
+        }
+        lines
+    }
+}
+
+impl Origin {
+    /// Capture the request, before its body is parsed by the transformer
+    pub fn new(request: &HttpRequest, body: &[u8]) -> Self {
+        let headers = request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+        Origin {
+            body: String::from_utf8_lossy(body).to_string(),
+            headers,
+        }
+    }
+}
+
+impl Message {
+    /// Create a message, which only consists of preformatted text
+    pub fn preformatted(status: Status, body: &str) -> Self {
+        Message {
+            status,
+            title: Vec::new(),
+            entries: Vec::new(),
+            body: Some(body.to_string()),
+            url: None,
+            language: Language::default(),
+            origin: None,
+        }
+    }
+
+    /// Remove all links with disallowed URL schemes, before the message is passed to any sink
+    pub fn sanitize(&mut self) {
+        let spans = self
+            .title
+            .iter_mut()
+            .chain(self.entries.iter_mut().flat_map(|entry| {
+                entry
+                    .headline
+                    .iter_mut()
+                    .chain(entry.details.iter_mut().flatten())
+            }));
+        for span in spans {
+            if span
+                .url
+                .as_deref()
+                .is_some_and(|url| escape::safe_url(url).is_none())
+            {
+                span.url = None;
+            }
+        }
+        if self
+            .url
+            .as_deref()
+            .is_some_and(|url| escape::safe_url(url).is_none())
+        {
+            self.url = None;
+        }
+        for entry in &mut self.entries {
+            entry
+                .links
+                .retain(|link| escape::safe_url(&link.url).is_some());
+            if entry
+                .image
+                .as_deref()
+                .is_some_and(|url| escape::safe_url(url).is_none())
+            {
+                entry.image = None;
+            }
+        }
+    }
+
+    /// Short plain text representation, consisting of the title (or the entries, if there is none)
+    pub fn text(&self) -> String {
+        if let Some(body) = &self.body {
+            return body.clone();
+        }
+        if !self.title.is_empty() {
+            return spans_to_text(&self.title);
+        }
+        self.entries
+            .iter()
+            .map(|entry| spans_to_text(&entry.headline))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// HTML representation, not available for preformatted messages
+    pub fn html(&self) -> Option<String> {
+        if self.body.is_some() {
+            return None;
+        }
+        let mut html = String::new();
+        if !self.title.is_empty() {
+            html += &format!("<h3>{}</h3>", spans_to_html(&self.title));
+        }
+        let mut group = None;
+        for entry in &self.entries {
+            if entry.group.is_some() && entry.group != group {
+                group = entry.group.clone();
+                html += &format!(
+                    "<h4>{}</h4>",
+                    escape::html_text(group.as_deref().unwrap_or(""))
+                );
+            }
+            let lines = entry.html_lines();
+            if entry.status == Status::Info {
+                // Informational entries (e.g. commits) are compact lists
+                for line in lines {
+                    html += &line;
+                    html += "<br>";
+                }
+            } else {
+                html += &format!("<p>{}</p>", lines.join("<br>"));
+            }
+        }
+        Some(html)
+    }
+
+    /// HTML representation, or the Markdown of preformatted messages
+    pub fn html_or_markdown(&self) -> String {
+        self.html().unwrap_or_else(|| self.markdown())
+    }
+
+    /// Markdown representation of the whole message, equivalent to the HTML
+    pub fn markdown(&self) -> String {
+        if let Some(body) = &self.body {
+            return body.clone();
+        }
+        let mut blocks = Vec::new();
+        if !self.title.is_empty() {
+            blocks.push(format!("### {}", spans_to_markdown(&self.title)));
+        }
+        blocks.extend(self.entry_blocks(
+            Entry::markdown_lines,
+            |group| format!("#### {}", escape_markdown(group)),
+            "  \n",
+        ));
+        blocks.join("\n\n")
+    }
+
+    /// Plain text representation of the whole message, equivalent to the HTML
+    pub fn plain_text(&self) -> String {
+        if let Some(body) = &self.body {
+            return body.clone();
+        }
+        let mut blocks = Vec::new();
+        if !self.title.is_empty() {
+            blocks.push(spans_to_text(&self.title));
+        }
+        blocks.extend(self.entry_blocks(Entry::text_lines, |group| group.to_string(), "\n"));
+        blocks.join("\n\n")
+    }
+
+    /// Join the lines of the entries, informational entries (e.g. commits) form a single compact block
+    fn entry_blocks(
+        &self,
+        lines: fn(&Entry) -> Vec<String>,
+        heading: fn(&str) -> String,
+        line_break: &str,
+    ) -> Vec<String> {
+        let mut blocks: Vec<String> = Vec::new();
+        let mut compact = false;
+        let mut group = None;
+        for entry in &self.entries {
+            if let Some(entry_group) = &entry.group
+                && entry.group != group
+            {
+                blocks.push(heading(entry_group));
+                group = entry.group.clone();
+                compact = false;
+            }
+            let entry_lines: Vec<String> = lines(entry)
+                .iter()
+                .map(|line| line.trim_end().to_string()) // e.g. the newline of commit messages
+                .collect();
+            let entry_lines = entry_lines.join(line_break);
+            match blocks.last_mut() {
+                Some(block) if compact && entry.status == Status::Info => {
+                    *block += line_break;
+                    *block += &entry_lines;
+                }
+                _ => blocks.push(entry_lines),
+            }
+            compact = entry.status == Status::Info;
+        }
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(entries: Vec<Entry>) -> Message {
+        Message {
+            status: Status::Firing,
+            title: vec![Span::plain("<script>alert('title')</script>")],
+            entries,
+            body: None,
+            url: Some("javascript:alert(1)".to_string()),
+            language: Language::default(),
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn alert_names_and_commit_messages_are_escaped() {
+        let mut alert = Entry::new(
+            Status::Firing,
+            vec![Span::bold("<script>alert(1)</script>")],
+        );
+        alert.labels.insert(
+            "team".to_string(),
+            "<img src=x onerror=alert(1)>".to_string(),
+        );
+        alert.group = Some("team=<b>ops</b>".to_string());
+        let mut commit = Entry::new(Status::Info, vec![Span::code("0123abcd")]);
+        commit.details.push(vec![Span::plain(
+            "Fix <script>document.cookie</script> & more",
+        )]);
+        let html = message(vec![alert, commit]).html().unwrap();
+        assert!(!html.contains("<script>"), "{}", html);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(html.contains("<h3>&lt;script&gt;alert('title')&lt;/script&gt;</h3>"));
+        assert!(html.contains("<b>&lt;script&gt;alert(1)&lt;/script&gt;</b>"));
+        assert!(html.contains("<h4>team=&lt;b&gt;ops&lt;/b&gt;</h4>"));
+        assert!(html.contains("Fix &lt;script&gt;document.cookie&lt;/script&gt; &amp; more"));
+    }
+
+    #[test]
+    fn quotes_cannot_break_out_of_hrefs() {
+        let span = Span::plain("dashboard").link("https://e.com/\" onmouseover=\"alert(1)");
+        assert_eq!(
+            spans_to_html(&[span]),
+            "<a href=\"https://e.com/&quot; onmouseover=&quot;alert(1)\">dashboard</a>"
+        );
+    }
+
+    #[test]
+    fn unsafe_links_are_dropped() {
+        let unsafe_urls = [
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            "java\tscript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+        ];
+        let mut entry = Entry::new(
+            Status::Firing,
+            unsafe_urls
+                .iter()
+                .map(|url| Span::bold("alert").link(url))
+                .collect(),
+        );
+        entry
+            .details
+            .push(vec![Span::plain("detail").link(unsafe_urls[0])]);
+        for url in unsafe_urls {
+            entry.links.push(Link {
+                label: "runbook".to_string(),
+                url: url.to_string(),
+            });
+        }
+        entry.links.push(Link {
+            label: "panel".to_string(),
+            url: "/d/abc?viewPanel=2".to_string(),
+        });
+        entry.image = Some("data:image/png;base64,AAAA".to_string());
+        let mut message = message(vec![entry]);
+
+        // Even without sanitizing, the renderers do not produce unsafe links
+        let html = message.html().unwrap();
+        assert!(!html.to_lowercase().contains("script:"), "{}", html);
+        assert!(!html.contains("data:"), "{}", html);
+        let markdown = message.markdown();
+        assert!(!markdown.to_lowercase().contains("script:"), "{}", markdown);
+
+        message.sanitize();
+        assert_eq!(message.url, None);
+        let entry = &message.entries[0];
+        assert!(entry.headline.iter().all(|span| span.url.is_none()));
+        assert!(entry.details[0][0].url.is_none());
+        assert_eq!(entry.links.len(), 1);
+        assert_eq!(entry.links[0].url, "/d/abc?viewPanel=2"); // relative URLs are kept
+        assert_eq!(entry.image, None);
+        assert!(
+            message
+                .html()
+                .unwrap()
+                .contains("<a href=\"/d/abc?viewPanel=2\">panel</a>")
+        );
+    }
+}
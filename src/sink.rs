@@ -0,0 +1,438 @@
+use crate::message::{Entry, Message, Span, Status};
+use futures::future;
+use log::error;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "amqp")]
+pub mod amqp;
+pub mod apprise;
+pub mod discord;
+pub mod email;
+pub mod file;
+pub mod gotify;
+pub mod hookshot;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod matrix;
+pub mod mattermost;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod ntfy;
+pub mod opsgenie;
+pub mod pagerduty;
+pub mod rocketchat;
+pub mod slack;
+pub mod stdout;
+pub mod teams;
+pub mod telegram;
+pub mod webhook;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SinkConfigTypes {
+    // Note that, the enum names will be used as YAML tag names
+    #[cfg(feature = "amqp")]
+    Amqp(amqp::AmqpSink),
+    Apprise(apprise::AppriseSink),
+    Discord(discord::DiscordSink),
+    Email(email::EmailSink),
+    File(file::FileSink),
+    Gotify(gotify::GotifySink),
+    Hookshot(hookshot::HookshotSink),
+    #[cfg(feature = "kafka")]
+    Kafka(kafka::KafkaSink),
+    Matrix(matrix::MatrixSink),
+    Mattermost(mattermost::MattermostSink),
+    #[cfg(feature = "mqtt")]
+    Mqtt(mqtt::MqttSink),
+    #[cfg(feature = "nats")]
+    Nats(nats::NatsSink),
+    Ntfy(ntfy::NtfySink),
+    Opsgenie(opsgenie::OpsgenieSink),
+    PagerDuty(pagerduty::PagerDutySink),
+    RocketChat(rocketchat::RocketChatSink),
+    Slack(slack::SlackSink),
+    Stdout(stdout::StdoutSink),
+    Teams(teams::TeamsSink),
+    Telegram(telegram::TelegramSink),
+    Webhook(webhook::WebhookSink),
+}
+
+impl SinkConfigTypes {
+    /// Submit the message to the sink, fitted into its length limit (if any)
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let Some((limit, render)) = self.limit() else {
+            return self.submit_part(message).await;
+        };
+        for part in LengthLimit::apply(limit, message, render) {
+            self.submit_part(&part).await?;
+        }
+        Ok(())
+    }
+
+    /// The length limit of the sink, and the rendering it applies to (if the sink supports one)
+    fn limit(&self) -> Option<(&Option<LengthLimit>, Render)> {
+        match self {
+            SinkConfigTypes::Email(inner_sink) => {
+                Some((&inner_sink.limit, Message::html_or_markdown))
+            }
+            SinkConfigTypes::Gotify(inner_sink) => Some((&inner_sink.limit, Message::markdown)),
+            SinkConfigTypes::Hookshot(inner_sink) => {
+                Some((&inner_sink.limit, Message::html_or_markdown))
+            }
+            SinkConfigTypes::Matrix(inner_sink) => {
+                Some((&inner_sink.limit, Message::html_or_markdown))
+            }
+            SinkConfigTypes::Mattermost(inner_sink) => Some((&inner_sink.limit, Message::markdown)),
+            SinkConfigTypes::Ntfy(inner_sink) => Some((&inner_sink.limit, Message::markdown)),
+            SinkConfigTypes::RocketChat(inner_sink) => Some((&inner_sink.limit, Message::markdown)),
+            SinkConfigTypes::Slack(inner_sink) => Some((&inner_sink.limit, Message::markdown)),
+            SinkConfigTypes::Teams(inner_sink) => Some((&inner_sink.limit, Message::markdown)),
+            _ => None,
+        }
+    }
+
+    /// Submit (a part of) the message to the sink (resolves the enum)
+    async fn submit_part(&self, message: &Message) -> Result<(), String> {
+        match self {
+            #[cfg(feature = "amqp")]
+            SinkConfigTypes::Amqp(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Apprise(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Discord(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Email(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::File(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Gotify(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Hookshot(inner_sink) => inner_sink.submit(message).await,
+            #[cfg(feature = "kafka")]
+            SinkConfigTypes::Kafka(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Matrix(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Mattermost(inner_sink) => inner_sink.submit(message).await,
+            #[cfg(feature = "mqtt")]
+            SinkConfigTypes::Mqtt(inner_sink) => inner_sink.submit(message).await,
+            #[cfg(feature = "nats")]
+            SinkConfigTypes::Nats(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Ntfy(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Opsgenie(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::PagerDuty(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::RocketChat(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Slack(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Stdout(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Teams(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Telegram(inner_sink) => inner_sink.submit(message).await,
+            SinkConfigTypes::Webhook(inner_sink) => inner_sink.submit(message).await,
+        }
+    }
+}
+
+impl SinkConfigTypes {
+    /// Build the HTTP clients of the sink, so that configuration errors show up at startup
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            SinkConfigTypes::Apprise(inner_sink) => inner_sink.validate(),
+            SinkConfigTypes::Discord(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Email(inner_sink) => inner_sink.validate(),
+            SinkConfigTypes::Gotify(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Hookshot(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Matrix(inner_sink) => inner_sink.validate(),
+            SinkConfigTypes::Mattermost(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Ntfy(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Opsgenie(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::PagerDuty(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::RocketChat(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Slack(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Teams(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Telegram(inner_sink) => inner_sink.http.client().map(|_| ()),
+            SinkConfigTypes::Webhook(inner_sink) => inner_sink.http.client().map(|_| ()),
+            _ => Ok(()), // no HTTP client
+        }
+    }
+}
+
+/// Submit the message to all sinks concurrently, returning the most recent error (if any)
+pub async fn submit_all<'a>(
+    sinks: impl IntoIterator<Item = &'a SinkConfigTypes>,
+    message: &Message,
+) -> Result<(), String> {
+    let results = future::join_all(sinks.into_iter().map(|sink| sink.submit(message))).await;
+    let mut result = Ok(());
+    for r in results {
+        if let Err(e) = r {
+            error!("Error while submitting message to sink: {:?}", e);
+            result = Err(e);
+        }
+    }
+    result
+}
+
+/// Shorten the text to at most `max_chars` characters, marking the cut with an ellipsis
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Rendering of a message, which determines its length (e.g. `Message::markdown`)
+pub type Render = fn(&Message) -> String;
+
+/// Maximum length of the rendered message, and what to do if it is exceeded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LengthLimit {
+    max_length: usize,               // in characters, e.g. of the HTML or Markdown
+    strategy: Option<LimitStrategy>, // (default: Truncate)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimitStrategy {
+    Truncate, // omit the last entries, linking to e.g. the dashboard or the compared commits instead
+    Split,    // send several messages, each containing as many (complete) entries as fit
+}
+
+impl LengthLimit {
+    /// Fit the message into the limit (if any), the rendering determines the length
+    pub fn apply(limit: &Option<Self>, message: &Message, render: Render) -> Vec<Message> {
+        let Some(limit) = limit else {
+            return vec![message.clone()];
+        };
+        let fits = |message: &Message| render(message).chars().count() <= limit.max_length;
+        if fits(message) {
+            return vec![message.clone()];
+        }
+        if let Some(body) = &message.body {
+            // Preformatted messages have no entries, which could be omitted
+            let mut message = message.clone();
+            message.body = Some(truncate(body, limit.max_length));
+            return vec![message];
+        }
+        match limit.strategy.unwrap_or(LimitStrategy::Truncate) {
+            LimitStrategy::Truncate => {
+                let mut truncated = message.clone();
+                for kept in (0..message.entries.len()).rev() {
+                    truncated.entries = message.entries[..kept].to_vec();
+                    let omitted = message.entries.len() - kept;
+                    let more = message
+                        .language
+                        .text("limit.more", &serde_json::json!({ "count": omitted }));
+                    let mut more = Span::plain(&more);
+                    if let Some(url) = &message.url {
+                        more = more.link(url);
+                    }
+                    truncated.entries.push(Entry::new(Status::Info, vec![more]));
+                    if fits(&truncated) {
+                        break;
+                    }
+                }
+                vec![truncated]
+            }
+            LimitStrategy::Split => {
+                let mut parts: Vec<Message> = Vec::new();
+                let mut part = Message {
+                    entries: Vec::new(),
+                    ..message.clone()
+                };
+                for entry in &message.entries {
+                    part.entries.push(entry.clone());
+                    if part.entries.len() > 1 && !fits(&part) {
+                        // Start the next part with this entry, an entry alone is never split
+                        part.entries.pop();
+                        let mut next = Message {
+                            entries: vec![entry.clone()],
+                            ..part.clone()
+                        };
+                        if parts.is_empty() {
+                            let continued = message
+                                .language
+                                .text("limit.continued", &serde_json::json!({}));
+                            next.title.push(Span::plain(&continued));
+                        }
+                        parts.push(part);
+                        part = next;
+                    }
+                }
+                parts.push(part);
+                parts
+            }
+        }
+    }
+}
+
+/// What to publish to message brokers, files, ... (as JSON)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JsonPayload {
+    Event,   // all values of the message (as used by the templates)
+    Message, // the rendered message, with "text" and "html"
+}
+
+impl JsonPayload {
+    pub fn value(&self, message: &Message) -> serde_json::Value {
+        match self {
+            JsonPayload::Event => crate::template::event(message),
+            JsonPayload::Message => {
+                serde_json::json!({ "text": message.text(), "html": message.html() })
+            }
+        }
+    }
+
+    pub fn render(&self, message: &Message) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(&self.value(message)).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Language;
+    use crate::message::spans_to_text;
+
+    fn message(headlines: &[&str]) -> Message {
+        Message {
+            status: Status::Firing,
+            title: vec![Span::plain("Alerts")],
+            entries: headlines
+                .iter()
+                .map(|headline| Entry::new(Status::Firing, vec![Span::plain(headline)]))
+                .collect(),
+            body: None,
+            url: Some("https://grafana.example.com/alerting".to_string()),
+            language: Language::default(),
+            origin: None,
+        }
+    }
+
+    fn limit(max_length: usize, strategy: LimitStrategy) -> Option<LengthLimit> {
+        Some(LengthLimit {
+            max_length,
+            strategy: Some(strategy),
+        })
+    }
+
+    fn headlines(message: &Message) -> Vec<String> {
+        message
+            .entries
+            .iter()
+            .map(|entry| spans_to_text(&entry.headline))
+            .collect()
+    }
+
+    fn length(message: &Message) -> usize {
+        message.markdown().chars().count()
+    }
+
+    const HEADLINES: [&str; 8] = [
+        "High CPU usage on db-1",
+        "Disk almost full on db-2",
+        "Memory pressure on web-1",
+        "Slow responses of the API",
+        "Errors in the payment service",
+        "Queue of the mailer is growing",
+        "Backup of the database failed",
+        "TLS certificate expires soon",
+    ];
+
+    #[test]
+    fn fitting_messages_are_unchanged() {
+        let message = message(&HEADLINES);
+        for strategy in [LimitStrategy::Truncate, LimitStrategy::Split] {
+            let parts = LengthLimit::apply(
+                &limit(length(&message), strategy),
+                &message,
+                Message::markdown,
+            );
+            assert_eq!(parts.len(), 1);
+            assert_eq!(parts[0].markdown(), message.markdown());
+        }
+    }
+
+    #[test]
+    fn truncating_links_the_omitted_entries() {
+        let message = message(&HEADLINES);
+        let max_length = length(&self::message(&HEADLINES[..4])) + 60; // room for the link
+        assert!(max_length < length(&message));
+        let parts = LengthLimit::apply(
+            &limit(max_length, LimitStrategy::Truncate),
+            &message,
+            Message::markdown,
+        );
+        assert_eq!(parts.len(), 1);
+        let truncated = &parts[0];
+        assert!(length(truncated) <= max_length);
+        let mut kept = headlines(truncated);
+        let more = kept.pop().unwrap();
+        assert!(!kept.is_empty());
+        assert!(headlines(&message).starts_with(&kept));
+        assert_eq!(
+            more,
+            format!("… and {} more", message.entries.len() - kept.len())
+        );
+        let link = &truncated.entries.last().unwrap().headline[0];
+        assert_eq!(link.url, message.url);
+    }
+
+    #[test]
+    fn splitting_keeps_all_entries_in_order() {
+        let message = message(&HEADLINES);
+        let max_length = length(&self::message(&HEADLINES[..3])) + 20;
+        let parts = LengthLimit::apply(
+            &limit(max_length, LimitStrategy::Split),
+            &message,
+            Message::markdown,
+        );
+        assert!(parts.len() > 1);
+        for (index, part) in parts.iter().enumerate() {
+            assert!(length(part) <= max_length);
+            let title = spans_to_text(&part.title);
+            match index {
+                0 => assert_eq!(title, "Alerts"),
+                _ => assert_eq!(title, "Alerts (continued)"),
+            }
+        }
+        let entries: Vec<String> = parts.iter().flat_map(headlines).collect();
+        assert_eq!(entries, headlines(&message));
+    }
+
+    #[test]
+    fn an_oversized_entry_is_sent_alone() {
+        let oversized = "x".repeat(500);
+        let message = message(&["CPU usage", &oversized, "Disk full"]);
+        let max_length = 200;
+
+        let parts = LengthLimit::apply(
+            &limit(max_length, LimitStrategy::Split),
+            &message,
+            Message::markdown,
+        );
+        assert_eq!(
+            parts.iter().map(headlines).collect::<Vec<_>>(),
+            vec![
+                vec!["CPU usage"],
+                vec![oversized.as_str()],
+                vec!["Disk full"]
+            ]
+        );
+
+        // Truncating keeps the entries before it, but never a part of it
+        let parts = LengthLimit::apply(
+            &limit(max_length, LimitStrategy::Truncate),
+            &message,
+            Message::markdown,
+        );
+        assert_eq!(parts.len(), 1);
+        assert!(length(&parts[0]) <= max_length);
+        assert_eq!(headlines(&parts[0]), vec!["CPU usage", "… and 2 more"]);
+    }
+
+    #[test]
+    fn preformatted_bodies_are_truncated() {
+        let message = Message::preformatted(Status::Info, &"x".repeat(500));
+        for strategy in [LimitStrategy::Truncate, LimitStrategy::Split] {
+            let parts = LengthLimit::apply(&limit(100, strategy), &message, Message::markdown);
+            assert_eq!(parts.len(), 1);
+            let body = parts[0].body.as_ref().unwrap();
+            assert_eq!(body.chars().count(), 100);
+            assert!(body.ends_with('…'));
+        }
+    }
+}
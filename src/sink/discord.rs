@@ -0,0 +1,267 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Message, Span, spans_to_markdown, spans_to_text};
+use crate::sink::truncate;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Limits of the embeds, see https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const MAX_TITLE_LENGTH: usize = 256;
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+const MAX_FIELDS: usize = 25;
+const MAX_FIELD_NAME_LENGTH: usize = 256;
+const MAX_FIELD_VALUE_LENGTH: usize = 1024;
+const MAX_EMBED_LENGTH: usize = 6000; // sum of all texts
+
+/// Longest wait when being rate-limited, the "retry_after" is given by the server
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscordSink {
+    uri: String,              // the webhook, e.g. "https://discord.com/api/webhooks/123/abc"
+    max_retries: Option<u32>, // (default: 3) when being rate-limited
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl DiscordSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        // Messages are sent one after another, to keep their order
+        for payload in self.payloads(message) {
+            self.send(&payload).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, payload: &serde_json::Value) -> Result<(), String> {
+        debug!("Submitting message to Discord: {:#?}", payload);
+        let client = self.http.client()?;
+        let mut retries = 0;
+        loop {
+            let response = client
+                .post(&self.uri)
+                .json(payload)
+                .send()
+                .await
+                .map_err(|e| e.without_url().to_string())?;
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            let body = response.text().await.unwrap_or_default();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                && retries < self.max_retries.unwrap_or(3)
+            {
+                let retry_after = retry_after(&body);
+                warn!("Rate-limited by Discord, retrying in {:?}", retry_after);
+                tokio::time::sleep(retry_after).await;
+                retries += 1;
+                continue;
+            }
+            return Err(format!("Discord responded with {}: {}", status, body));
+        }
+    }
+
+    /// Render the message as embeds (one field per entry), split into multiple payloads if too long
+    fn payloads(&self, message: &Message) -> Vec<serde_json::Value> {
+        let title = truncate(&spans_to_text(&message.title), MAX_TITLE_LENGTH);
+        let url = message.title.iter().find_map(|span| span.url.clone());
+        let color = u32::from_str_radix(message.status.color().trim_start_matches('#'), 16)
+            .unwrap_or_default();
+
+        let fields: Vec<(String, String)> = message
+            .entries
+            .iter()
+            .map(|entry| {
+                // Discord does not render links in the field name, so they are listed below
+                let headline: Vec<Span> = entry
+                    .headline_with_icon()
+                    .into_iter()
+                    .map(|span| Span { url: None, ..span })
+                    .collect();
+                let name = spans_to_markdown(&headline).trim().to_string();
+                let mut lines: Vec<String> = entry
+                    .details
+                    .iter()
+                    .map(|detail| spans_to_markdown(detail))
+                    .collect();
+                let mut links: Vec<String> = entry
+                    .headline
+                    .iter()
+                    .filter(|span| span.url.is_some())
+                    .map(|span| spans_to_markdown(std::slice::from_ref(span)))
+                    .collect();
+                links.extend(
+                    entry
+                        .links
+                        .iter()
+                        .map(|link| format!("[{}]({})", link.label, link.url)),
+                );
+                if !links.is_empty() {
+                    lines.push("→ ".to_string() + &links.join(", "));
+                }
+                let value = if lines.is_empty() {
+                    "\u{200b}".to_string() // the value must not be empty
+                } else {
+                    lines.join("\n")
+                };
+                (
+                    truncate(&name, MAX_FIELD_NAME_LENGTH),
+                    truncate(&value, MAX_FIELD_VALUE_LENGTH),
+                )
+            })
+            .collect();
+
+        let new_embed = |continued: bool| {
+            let mut embed = serde_json::json!({ "color": color, "fields": [] });
+            if !title.is_empty() {
+                embed["title"] = if continued {
+                    let continued = message
+                        .language
+                        .text("limit.continued", &serde_json::json!({}));
+                    truncate(&(title.clone() + &continued), MAX_TITLE_LENGTH).into()
+                } else {
+                    title.as_str().into()
+                };
+            }
+            if let Some(url) = &url {
+                embed["url"] = url.as_str().into();
+            }
+            embed
+        };
+
+        let mut embeds = Vec::new();
+        let mut embed = new_embed(false);
+        let mut length = title.chars().count();
+        if let Some(body) = &message.body {
+            // A long (preformatted) body is continued in the description of the next embeds
+            for (index, description) in split_lines(body, MAX_DESCRIPTION_LENGTH)
+                .into_iter()
+                .enumerate()
+            {
+                if index > 0 {
+                    embeds.push(embed);
+                    embed = new_embed(true);
+                    length = embed["title"].as_str().unwrap_or_default().chars().count();
+                }
+                length += description.chars().count();
+                embed["description"] = description.into();
+            }
+        }
+        let mut field_count = 0;
+        for (name, value) in fields {
+            let field_length = name.chars().count() + value.chars().count();
+            if field_count == MAX_FIELDS || length + field_length > MAX_EMBED_LENGTH {
+                embeds.push(embed);
+                embed = new_embed(true);
+                length = embed["title"].as_str().unwrap_or_default().chars().count();
+                field_count = 0;
+            }
+            if let Some(fields) = embed["fields"].as_array_mut() {
+                fields.push(serde_json::json!({ "name": name, "value": value }));
+            }
+            length += field_length;
+            field_count += 1;
+        }
+        embeds.push(embed);
+
+        embeds
+            .into_iter()
+            .map(|embed| serde_json::json!({ "embeds": [embed] }))
+            .collect()
+    }
+}
+
+/// Split the text into parts of at most `max_chars` characters, preferably at line breaks
+fn split_lines(text: &str, max_chars: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest: Vec<char> = text.chars().collect();
+    while rest.len() > max_chars {
+        // Cut after the last line break within the limit (or within a line, if there is none)
+        let cut = rest[..max_chars]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map(|position| position + 1)
+            .filter(|position| *position > 1)
+            .unwrap_or(max_chars);
+        parts.push(
+            rest[..cut]
+                .iter()
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+        );
+        rest = rest[cut..].to_vec();
+    }
+    parts.push(rest.into_iter().collect());
+    parts
+}
+
+/// How long to wait before retrying, as requested by the response (default: 1 second)
+fn retry_after(body: &str) -> Duration {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("retry_after").and_then(|v| v.as_f64()))
+        .and_then(|seconds| match Duration::try_from_secs_f64(seconds) {
+            Ok(duration) => Some(duration.min(MAX_RETRY_AFTER)),
+            Err(_) if seconds > 0.0 => Some(MAX_RETRY_AFTER), // too large (or infinite)
+            Err(_) => None,                                   // negative or NaN
+        })
+        .unwrap_or(Duration::from_secs(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_lines_prefers_line_breaks() {
+        let text = "a".repeat(30) + "\n" + &"b".repeat(30) + "\n" + &"c".repeat(10);
+        assert_eq!(
+            split_lines(&text, 70),
+            vec!["a".repeat(30) + "\n" + &"b".repeat(30), "c".repeat(10)]
+        );
+    }
+
+    #[test]
+    fn split_lines_cuts_long_lines() {
+        let parts = split_lines(&"ä".repeat(250), 100);
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| part.chars().count() <= 100));
+        assert_eq!(parts.concat(), "ä".repeat(250));
+    }
+
+    #[test]
+    fn long_body_is_split_into_embeds() {
+        let sink: DiscordSink = serde_json::from_value(serde_json::json!({ "uri": "" })).unwrap();
+        let body = ("line\n".repeat(1000) + "end").repeat(3);
+        let payloads = sink.payloads(&Message::preformatted(crate::message::Status::Info, &body));
+        assert_eq!(payloads.len(), 4);
+        let descriptions: Vec<&str> = payloads
+            .iter()
+            .map(|payload| payload["embeds"][0]["description"].as_str().unwrap())
+            .collect();
+        assert!(
+            descriptions
+                .iter()
+                .all(|d| d.chars().count() <= MAX_DESCRIPTION_LENGTH)
+        );
+        assert_eq!(descriptions.concat().matches("line").count(), 3000);
+    }
+
+    #[test]
+    fn retry_after_is_bounded() {
+        assert_eq!(
+            retry_after(r#"{"retry_after": 0.5}"#),
+            Duration::from_millis(500)
+        );
+        assert_eq!(retry_after(r#"{"retry_after": 1e30}"#), MAX_RETRY_AFTER);
+        for body in [
+            r#"{"retry_after": -1}"#,
+            r#"{"retry_after": "soon"}"#,
+            r#"{"message": "You are being rate limited."}"#,
+            "<html>",
+        ] {
+            assert_eq!(retry_after(body), Duration::from_secs(1), "{}", body);
+        }
+    }
+}
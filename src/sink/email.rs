@@ -0,0 +1,202 @@
+use crate::message::Message;
+use crate::sink::LengthLimit;
+use crate::template;
+use lettre::message::{Mailbox, MultiPart, SinglePart, header::ContentType};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::LinkedList;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailSink {
+    host: String,             // the SMTP server
+    port: Option<u16>,        // (default: 587 for StartTls, 465 for Implicit and 25 for Off)
+    tls: Option<EmailTls>,    // (default: StartTls)
+    verify_tls: Option<bool>, // (default: true) DANGEROUS, if disabled
+    username: Option<String>, // enables the authentication
+    password: Option<String>,
+    timeout: Option<u64>,    // (default: 10) in seconds
+    from: String,            // e.g. "Alerts <alerts@example.com>"
+    subject: Option<String>, // (default: "{{title}}") template, falls back to the first line
+    to: LinkedList<String>,
+    cc: Option<LinkedList<String>>,
+    bcc: Option<LinkedList<String>>,
+    pub(crate) limit: Option<LengthLimit>,
+}
+
+/// How to secure the connection to the SMTP server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmailTls {
+    StartTls, // upgrade the plain connection, the server must support it
+    Implicit, // TLS from the start (also known as SMTPS)
+    Off,      // DANGEROUS, everything is sent in plain text
+}
+
+impl EmailSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let email = self.email(message)?;
+        debug!("Submitting message via email: {:#?}", email);
+
+        self.transport()?
+            .send(email)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Parse the addresses and set up the transport, so that typos are reported at startup
+    pub fn validate(&self) -> Result<(), String> {
+        parse_mailbox(&self.from)?;
+        for address in self
+            .to
+            .iter()
+            .chain(self.cc.iter().flatten())
+            .chain(self.bcc.iter().flatten())
+        {
+            parse_mailbox(address)?;
+        }
+        self.transport().map(|_| ())
+    }
+
+    fn email(&self, message: &Message) -> Result<lettre::Message, String> {
+        let text = message.plain_text();
+        let mut subject = template::render(
+            self.subject.as_deref().unwrap_or("{{title}}"),
+            &template::context(message),
+        );
+        if subject.trim().is_empty() {
+            subject = text.lines().next().unwrap_or_default().to_string();
+        }
+
+        let mut builder = lettre::Message::builder()
+            .from(parse_mailbox(&self.from)?)
+            .subject(subject);
+        for to in &self.to {
+            builder = builder.to(parse_mailbox(to)?);
+        }
+        for cc in self.cc.iter().flatten() {
+            builder = builder.cc(parse_mailbox(cc)?);
+        }
+        for bcc in self.bcc.iter().flatten() {
+            builder = builder.bcc(parse_mailbox(bcc)?);
+        }
+        match message.html() {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(text, html)),
+            None => builder.singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(text),
+            ),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+        let mode = self.tls.unwrap_or(EmailTls::StartTls);
+        let parameters = TlsParameters::builder(self.host.clone())
+            .dangerous_accept_invalid_certs(!self.verify_tls.unwrap_or(true))
+            .build_rustls()
+            .map_err(|e| e.to_string())?;
+        let (tls, default_port) = match mode {
+            EmailTls::StartTls => (Tls::Required(parameters), 587),
+            EmailTls::Implicit => (Tls::Wrapper(parameters), 465),
+            EmailTls::Off => (Tls::None, 25),
+        };
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host)
+            .port(self.port.unwrap_or(default_port))
+            .tls(tls)
+            .timeout(Some(std::time::Duration::from_secs(
+                self.timeout.unwrap_or(10),
+            )));
+        if let Some(username) = &self.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                self.password.clone().unwrap_or_default(),
+            ));
+        }
+        Ok(builder.build())
+    }
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, String> {
+    address
+        .parse()
+        .map_err(|e| format!("Invalid email address \"{}\": {}", address, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Span, Status};
+
+    fn sink(config: serde_json::Value) -> EmailSink {
+        let mut value = serde_json::json!({
+            "host": "smtp.example.com",
+            "from": "Alerts <alerts@example.com>",
+            "to": ["oncall@example.com"],
+        });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(config.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn subject(sink: &EmailSink, message: &Message) -> String {
+        let email = sink.email(message).unwrap();
+        let formatted = String::from_utf8(email.formatted()).unwrap();
+        formatted
+            .lines()
+            .find_map(|line| line.strip_prefix("Subject: "))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn subject_is_rendered_from_the_template() {
+        let mut entry = Entry::new(Status::Firing, vec![Span::bold("HighCPU")]);
+        entry
+            .labels
+            .insert("severity".to_string(), "critical".to_string());
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.title = vec![Span::plain("Grafana")];
+        message.entries = vec![entry];
+
+        assert_eq!(subject(&sink(serde_json::json!({})), &message), "Grafana");
+        let templated = sink(serde_json::json!({
+            "subject": "[{{status}}] {{title}}: {{count}} ({{severity}})",
+        }));
+        assert_eq!(
+            subject(&templated, &message),
+            "[firing] Grafana: 1 (critical)"
+        );
+    }
+
+    #[test]
+    fn empty_subjects_fall_back_to_the_first_line() {
+        let message = Message::preformatted(Status::Firing, "Disk full\non db1");
+        assert_eq!(subject(&sink(serde_json::json!({})), &message), "Disk full");
+    }
+
+    #[test]
+    fn addresses_are_validated() {
+        let valid = sink(serde_json::json!({ "cc": ["Team <team@example.com>"] }));
+        assert_eq!(valid.validate(), Ok(()));
+        for config in [
+            serde_json::json!({ "from": "alerts" }),
+            serde_json::json!({ "to": ["oncall@example.com", "oncall@"] }),
+            serde_json::json!({ "bcc": ["<unclosed@example.com"] }),
+        ] {
+            let error = sink(config.clone()).validate().unwrap_err();
+            assert!(
+                error.starts_with("Invalid email address"),
+                "{}: {}",
+                config,
+                error
+            );
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::message::Message;
+use crate::sink::JsonPayload;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Locks of the files, so that the writes of all workers and sinks with the same path are serialized
+static LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSink {
+    path: String,                 // e.g. "/var/log/webhooks/notifications.jsonl"
+    payload: Option<JsonPayload>, // (default: Event)
+    max_size: Option<u64>,        // in bytes, rotate before the file would exceed it
+    rotate_every: Option<u64>,    // in seconds, e.g. 86400 to rotate daily (at midnight UTC)
+    max_files: Option<u32>,       // (default: 5) rotated files to keep, e.g. "<path>.1"
+}
+impl FileSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?;
+        let mut value = self.payload.unwrap_or(JsonPayload::Event).value(message);
+        value["timestamp"] = now.as_secs().into();
+        let mut line = serde_json::to_string(&value).map_err(|e| e.to_string())?;
+        line.push('\n');
+
+        // The file system may be slow, so the workers are not blocked by it
+        let sink = self.clone();
+        tokio::task::spawn_blocking(move || sink.append(&line, now.as_secs()))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    fn append(&self, line: &str, now: u64) -> Result<(), String> {
+        let lock = self.lock()?;
+        let _guard = lock.lock().map_err(|e| e.to_string())?;
+        if self.needs_rotation(line.len() as u64, now) {
+            self.rotate()?;
+        }
+        debug!("Appending message to {}", self.path);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write to {}: {}", self.path, e))
+    }
+
+    /// Get the lock of the file, which is shared by all sinks with the same path
+    fn lock(&self) -> Result<Arc<Mutex<()>>, String> {
+        let path = std::path::absolute(&self.path).map_err(|e| e.to_string())?;
+        let mut locks = LOCKS
+            .get_or_init(Default::default)
+            .lock()
+            .map_err(|e| e.to_string())?;
+        Ok(locks.entry(path).or_default().clone())
+    }
+
+    fn needs_rotation(&self, additional_size: u64, now: u64) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return false; // nothing to rotate (yet)
+        };
+        if metadata.len() == 0 {
+            return false;
+        }
+        if let Some(max_size) = self.max_size
+            && metadata.len() + additional_size > max_size
+        {
+            return true;
+        }
+        if let Some(interval) = self.rotate_every.filter(|interval| *interval > 0) {
+            // Rotate, if the file was last written in a previous interval
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|modified| modified.as_secs())
+                .unwrap_or(now);
+            return modified / interval != now / interval;
+        }
+        false
+    }
+
+    /// Shift the rotated files ("<path>.1" becomes "<path>.2", ...) and drop the oldest one
+    fn rotate(&self) -> Result<(), String> {
+        let max_files = self.max_files.unwrap_or(5);
+        debug!("Rotating {}", self.path);
+        if max_files == 0 {
+            return fs::remove_file(&self.path).map_err(|e| e.to_string());
+        }
+        let _ = fs::remove_file(format!("{}.{}", self.path, max_files));
+        for index in (1..max_files).rev() {
+            let _ = fs::rename(
+                format!("{}.{}", self.path, index),
+                format!("{}.{}", self.path, index + 1),
+            );
+        }
+        fs::rename(&self.path, format!("{}.1", self.path))
+            .map_err(|e| format!("Failed to rotate {}: {}", self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Status;
+
+    #[actix_web::test]
+    async fn sinks_with_the_same_path_do_not_interleave() {
+        let directory = std::env::temp_dir().join(format!("file-sink-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory
+            .join("messages.jsonl")
+            .to_string_lossy()
+            .to_string();
+        let sink = |payload| FileSink {
+            path: path.clone(),
+            payload: Some(payload),
+            max_size: Some(4096),
+            rotate_every: None,
+            max_files: Some(100),
+        };
+        let (first, second) = (sink(JsonPayload::Event), sink(JsonPayload::Message));
+        let message = Message::preformatted(Status::Info, &"x".repeat(300));
+        let writes = (0..50).map(|index| match index % 2 {
+            0 => first.submit(&message),
+            _ => second.submit(&message),
+        });
+        for result in futures::future::join_all(writes).await {
+            result.unwrap();
+        }
+
+        let mut lines = 0;
+        for entry in fs::read_dir(&directory).unwrap() {
+            let content = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(content.len() <= 4096);
+            for line in content.lines() {
+                serde_json::from_str::<serde_json::Value>(line).unwrap();
+                lines += 1;
+            }
+        }
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(lines, 50);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Message, Status, spans_to_text};
+use crate::sink::LengthLimit;
+use crate::sink::ntfy::{body_markdown, links};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GotifySink {
+    uri: String,          // the server, e.g. "https://gotify.example.com"
+    token: String,        // the token of the application
+    priority: Option<u8>, // overrides the priority derived from the status (0-10)
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl GotifySink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let payload = self.payload(message);
+        debug!("Submitting message to Gotify: {:#?}", payload);
+        let response = self
+            .http
+            .client()?
+            .post(format!("{}/message", self.uri.trim_end_matches('/')))
+            .header("X-Gotify-Key", &self.token)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Gotify responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    fn payload(&self, message: &Message) -> serde_json::Value {
+        let mut payload = serde_json::json!({
+            "message": body_markdown(message),
+            "priority": self.priority.unwrap_or(match message.status {
+                Status::Firing => 8,
+                Status::Pending => 5,
+                Status::Resolved => 2,
+                Status::Unknown | Status::Info => 4,
+            }),
+            "extras": {
+                "client::display": { "contentType": "text/markdown" },
+            },
+        });
+        if !message.title.is_empty() {
+            payload["title"] = spans_to_text(&message.title).into();
+        }
+        if let Some((_, url)) = links(message).first() {
+            payload["extras"]["client::notification"] =
+                serde_json::json!({ "click": { "url": url } });
+        }
+        payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Link, Span};
+
+    fn sink(priority: Option<u8>) -> GotifySink {
+        serde_json::from_value(serde_json::json!({
+            "uri": "https://gotify.example.com/",
+            "token": "T0K3N",
+            "priority": priority,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn priority_is_derived_from_the_status() {
+        for (status, expected) in [
+            (Status::Firing, 8),
+            (Status::Pending, 5),
+            (Status::Resolved, 2),
+            (Status::Unknown, 4),
+            (Status::Info, 4),
+        ] {
+            let payload = sink(None).payload(&Message::preformatted(status, "Disk full"));
+            assert_eq!(payload["priority"], expected, "{:?}", status);
+        }
+        let payload = sink(Some(10)).payload(&Message::preformatted(Status::Resolved, "Fine"));
+        assert_eq!(payload["priority"], 10);
+    }
+
+    #[test]
+    fn the_first_link_is_opened_on_click() {
+        let mut entry = Entry::new(Status::Firing, vec![Span::bold("HighCPU")]);
+        entry.links.push(Link {
+            label: "Panel".to_string(),
+            url: "https://grafana.example.com/d/abc".to_string(),
+        });
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.title = vec![Span::plain("Grafana")];
+        message.entries = vec![entry];
+
+        let payload = sink(None).payload(&message);
+        assert_eq!(payload["title"], "Grafana");
+        assert_eq!(
+            payload["extras"]["client::notification"]["click"]["url"],
+            "https://grafana.example.com/d/abc"
+        );
+        assert_eq!(
+            payload["extras"]["client::display"]["contentType"],
+            "text/markdown"
+        );
+    }
+}
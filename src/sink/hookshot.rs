@@ -0,0 +1,42 @@
+use crate::http::HttpClientConfig;
+use crate::message::Message;
+use crate::sink::LengthLimit;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HookshotMessage {
+    text: String,             // automatically converted from Markdown to HTML
+    html: Option<String>,     // if not provided, the text will be (converted and) used
+    username: Option<String>, // will be prepended to the message
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookshotSink {
+    pub uri: String,
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    pub http: HttpClientConfig,
+}
+impl HookshotSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let message = HookshotMessage {
+            text: message.markdown(), // the full content, in case the HTML is not used
+            html: message.html(),
+            username: None,
+        };
+        debug!(
+            "Submitting message to Hookshot (via {}): {:#?}",
+            self.uri, message
+        );
+        self.http
+            .client()?
+            .post(&self.uri)
+            .body(serde_json::to_string(&message).map_err(|e| e.to_string())?)
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        Ok(())
+    }
+}
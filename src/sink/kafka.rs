@@ -0,0 +1,103 @@
+use crate::message::Message;
+use crate::sink::JsonPayload;
+use crate::template;
+use log::debug;
+use rskafka::client::partition::{Compression, UnknownTopicHandling};
+use rskafka::client::{Client, ClientBuilder, Credentials, SaslConfig};
+use rskafka::record::Record;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaSink {
+    brokers: Vec<String>,     // bootstrap brokers, e.g. ["kafka.example.com:9092"]
+    username: Option<String>, // enables SASL/PLAIN
+    password: Option<String>,
+    topic: String,                // template, e.g. "alerts.{{severity}}"
+    partition: Option<i32>,       // (default: 0)
+    key: Option<String>,          // template, e.g. "{{entries.0.fingerprint}}"
+    payload: Option<JsonPayload>, // (default: Event)
+    timeout: Option<u64>,         // (default: 10) in seconds
+    #[serde(skip)]
+    client: Arc<OnceCell<Client>>, // shared by all clones of this config, reconnects by itself
+}
+impl KafkaSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let context = template::context(message);
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| e.to_string())?;
+        // Topics are limited to alphanumeric characters, ".", "_" and "-"
+        let topic = template::render_with(&self.topic, &context, topic_value);
+        let record = Record {
+            key: self
+                .key
+                .as_ref()
+                .map(|key| template::render(key, &context).into_bytes()),
+            value: Some(self.payload.unwrap_or(JsonPayload::Event).render(message)?),
+            headers: BTreeMap::new(),
+            timestamp: rskafka::chrono::DateTime::from_timestamp_millis(now.as_millis() as i64)
+                .unwrap_or_default(),
+        };
+        let timeout = Duration::from_secs(self.timeout.unwrap_or(10));
+        tokio::time::timeout(timeout, self.produce(topic, record))
+            .await
+            .map_err(|_| "Timed out while publishing to Kafka".to_string())?
+    }
+
+    async fn produce(&self, topic: String, record: Record) -> Result<(), String> {
+        let client = self.client.get_or_try_init(|| self.connect()).await?;
+        debug!("Publishing message to Kafka topic {}", topic);
+        client
+            .partition_client(
+                topic,
+                self.partition.unwrap_or(0),
+                UnknownTopicHandling::Error,
+            )
+            .await
+            .map_err(|e| e.to_string())?
+            .produce(vec![record], Compression::NoCompression)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    async fn connect(&self) -> Result<Client, String> {
+        let mut builder = ClientBuilder::new(self.brokers.clone());
+        if let Some(username) = &self.username {
+            builder = builder.sasl_config(SaslConfig::Plain(Credentials::new(
+                username.clone(),
+                self.password.clone().unwrap_or_default(),
+            )));
+        }
+        builder.build().await.map_err(|e| e.to_string())
+    }
+}
+
+/// Replace the characters, which are not allowed in topic names
+fn topic_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_values_are_valid() {
+        let context = serde_json::json!({ "team": "a/b c:ä", "status": "firing" });
+        assert_eq!(
+            template::render_with("alerts.{{status}}.{{team}}", &context, topic_value),
+            "alerts.firing.a_b_c__"
+        );
+    }
+}
@@ -0,0 +1,660 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Message, Status};
+use crate::sink::LengthLimit;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Counter to make the transaction ids unique within this process
+static TRANSACTION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// At most this many firing messages are remembered, the oldest ones are forgotten first
+const MAX_REMEMBERED_EVENTS: usize = 10000;
+
+/// At most this many redirects are followed when fetching an image
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixSink {
+    homeserver: String,                    // e.g. "https://matrix.example.com"
+    access_token: String,                  // the (bot) user must already be joined to the room
+    room_id: String,                       // e.g. "!AbCdEf:example.com"
+    notice: Option<bool>,                  // (default: true) "m.notice" instead of "m.text"
+    max_retries: Option<u32>,              // (default: 3) when being rate-limited
+    on_resolve: Option<MatrixResolveMode>, // (default: Message)
+    remember_for: Option<u64>,             // (default: 7 days) in seconds, of the firing messages
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    images: MatrixImages,
+    #[serde(default)]
+    http: HttpClientConfig,
+    #[serde(skip)]
+    events: Arc<Mutex<HashMap<String, (String, Instant)>>>, // fingerprint -> event id of the firing message
+}
+
+/// How to post a message, which only contains resolved alerts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatrixResolveMode {
+    Message, // as new top-level message
+    Thread,  // as thread reply to the message of the firing alert
+    Edit, // as edit ("m.replace") of the message of the firing alerts, once all of them are resolved (otherwise as Thread)
+}
+
+/// How to post the images (e.g. screenshots) of the alerts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MatrixImages {
+    upload: Option<bool>, // (default: true) as "m.image" events, otherwise they are only linked
+    max_size: Option<u64>, // (default: 5 MiB) in bytes, larger images are only linked
+    allowed_hosts: Option<Vec<String>>, // (default: the host of the message's link, e.g. Grafana) to fetch from
+    #[serde(default)]
+    http: HttpClientConfig, // used to fetch the images, e.g. its timeout
+}
+
+impl MatrixImages {
+    /// Whether images may be fetched from the URL, by default only from the host of the message's link
+    fn is_allowed(&self, url: &reqwest::Url, message: &Message) -> bool {
+        let Some(host) = url
+            .host_str()
+            .filter(|_| ["http", "https"].contains(&url.scheme()))
+        else {
+            return false;
+        };
+        match &self.allowed_hosts {
+            Some(hosts) => hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host)),
+            None => message
+                .url
+                .as_deref()
+                .and_then(|link| reqwest::Url::parse(link).ok())
+                .is_some_and(|link| {
+                    link.host_str() == Some(host)
+                        && link.port_or_known_default() == url.port_or_known_default()
+                }),
+        }
+    }
+
+    /// Request the image, following redirects only to allowed hosts
+    async fn fetch(&self, image: &str, message: &Message) -> Result<reqwest::Response, String> {
+        let client = self.http.client_without_redirects()?;
+        let mut url = reqwest::Url::parse(image).map_err(|e| e.to_string())?;
+        for _ in 0..=MAX_REDIRECTS {
+            // The URL is given by the payload, so it must not point to e.g. internal services
+            if !self.is_allowed(&url, message) {
+                return Err(format!("Not allowed to fetch images from {}", url));
+            }
+            let response = client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            if !response.status().is_redirection() {
+                return Ok(response);
+            }
+            let location = response
+                .headers()
+                .get("Location")
+                .and_then(|v| v.to_str().ok())
+                .ok_or(format!("Fetching responded with {}", response.status()))?;
+            url = url.join(location).map_err(|e| e.to_string())?;
+        }
+        Err(format!("More than {} redirects", MAX_REDIRECTS))
+    }
+}
+
+impl MatrixSink {
+    /// Build the HTTP clients (of the homeserver and to fetch the images)
+    pub fn validate(&self) -> Result<(), String> {
+        self.http.client()?;
+        self.images.http.client_without_redirects().map(|_| ())
+    }
+
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let content = self.related_content(message)?;
+        let event_id = self.send_event("m.room.message", &content).await?;
+        if self.images.upload.unwrap_or(true) {
+            for image in message
+                .entries
+                .iter()
+                .filter_map(|entry| entry.image.as_ref())
+            {
+                // The image is optional, so the message is delivered nevertheless
+                if let Err(e) = self.send_image(image, message).await {
+                    warn!("Failed to post the image {} to Matrix: {}", image, e);
+                }
+            }
+        }
+        self.remember(message, &event_id)
+    }
+
+    /// Create the content of the event, relating it to the original message if this one only resolves its alerts
+    fn related_content(&self, message: &Message) -> Result<serde_json::Value, String> {
+        let content = self.content(message);
+        let fingerprints: Vec<&String> = message
+            .entries
+            .iter()
+            .filter_map(|entry| entry.fingerprint.as_ref())
+            .collect();
+        let is_resolution = !message.entries.is_empty()
+            && message
+                .entries
+                .iter()
+                .all(|entry| entry.status == Status::Resolved);
+        let on_resolve = self.on_resolve.unwrap_or(MatrixResolveMode::Message);
+        if !is_resolution || on_resolve == MatrixResolveMode::Message {
+            return Ok(content);
+        }
+
+        let events = self.events.lock().map_err(|e| e.to_string())?;
+        let Some(event_id) = fingerprints
+            .iter()
+            .find_map(|fingerprint| events.get(*fingerprint))
+            .filter(|(_, sent)| sent.elapsed() < self.remember_for())
+            .map(|(event_id, _)| event_id.clone())
+        else {
+            return Ok(content);
+        };
+        // The edit replaces the whole original message, so it must not contain alerts, which are still firing
+        let still_firing = events.iter().any(|(fingerprint, (other, _))| {
+            *other == event_id && !fingerprints.contains(&fingerprint)
+        });
+        if on_resolve == MatrixResolveMode::Edit && !still_firing {
+            let mut fallback = self.content(message);
+            fallback["body"] = format!("* {}", message.text()).into();
+            fallback["m.new_content"] = content;
+            fallback["m.relates_to"] = serde_json::json!({
+                "rel_type": "m.replace",
+                "event_id": event_id,
+            });
+            return Ok(fallback);
+        }
+        let mut content = content;
+        content["m.relates_to"] = serde_json::json!({
+            "rel_type": "m.thread",
+            "event_id": event_id,
+            "is_falling_back": true,
+            "m.in_reply_to": { "event_id": event_id },
+        });
+        Ok(content)
+    }
+
+    /// Remember (or forget) the message of the alerts, only needed to relate their resolution to it
+    fn remember(&self, message: &Message, event_id: &str) -> Result<(), String> {
+        if self.on_resolve.unwrap_or(MatrixResolveMode::Message) == MatrixResolveMode::Message {
+            return Ok(());
+        }
+        let mut events = self.events.lock().map_err(|e| e.to_string())?;
+        // Alerts may never be resolved (or their resolution is lost), so old messages are forgotten
+        let remember_for = self.remember_for();
+        events.retain(|_, (_, sent)| sent.elapsed() < remember_for);
+        for entry in &message.entries {
+            if let Some(fingerprint) = &entry.fingerprint {
+                if entry.status == Status::Resolved {
+                    events.remove(fingerprint);
+                    continue;
+                }
+                if events.len() >= MAX_REMEMBERED_EVENTS && !events.contains_key(fingerprint) {
+                    let oldest = events
+                        .iter()
+                        .min_by_key(|(_, (_, sent))| *sent)
+                        .map(|(fingerprint, _)| fingerprint.clone());
+                    if let Some(oldest) = oldest {
+                        events.remove(&oldest);
+                    }
+                }
+                events.insert(fingerprint.clone(), (event_id.to_string(), Instant::now()));
+            }
+        }
+        Ok(())
+    }
+
+    /// How long the firing messages are remembered, to thread or edit their resolution
+    fn remember_for(&self) -> Duration {
+        Duration::from_secs(self.remember_for.unwrap_or(7 * 24 * 60 * 60))
+    }
+
+    /// Create the content of a "m.room.message" event for the message
+    fn content(&self, message: &Message) -> serde_json::Value {
+        let mut content = serde_json::json!({
+            "msgtype": if self.notice.unwrap_or(true) { "m.notice" } else { "m.text" },
+            "body": message.text(),
+        });
+        if let Some(html) = message.html() {
+            content["format"] = "org.matrix.custom.html".into();
+            content["formatted_body"] = html.into();
+        }
+        content
+    }
+
+    /// Fetch the image, upload it to the media repository and post it as "m.image" event
+    async fn send_image(&self, image: &str, message: &Message) -> Result<(), String> {
+        let max_size = self.images.max_size.unwrap_or(5 * 1024 * 1024);
+        let mut response = self.images.fetch(image, message).await?;
+        if !response.status().is_success() {
+            return Err(format!("Fetching responded with {}", response.status()));
+        }
+        let mimetype = response
+            .headers()
+            .get("Content-Type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        if !mimetype.starts_with("image/") {
+            return Err(format!("Not an image, but {:?}", mimetype));
+        }
+        if response
+            .content_length()
+            .is_some_and(|length| length > max_size)
+        {
+            return Err(format!("Larger than {} bytes", max_size));
+        }
+        // Read it in chunks, as the length may be unknown in advance
+        let mut data = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            data.extend_from_slice(&chunk);
+            if data.len() as u64 > max_size {
+                return Err(format!("Larger than {} bytes", max_size));
+            }
+        }
+
+        let filename = reqwest::Url::parse(image)
+            .ok()
+            .and_then(|url| url.path_segments()?.next_back().map(|s| s.to_string()))
+            .filter(|filename| !filename.is_empty())
+            .unwrap_or("image".to_string());
+        let mut url = self.url(&["_matrix", "media", "v3", "upload"])?;
+        url.query_pairs_mut().append_pair("filename", &filename);
+        let size = data.len();
+        let response = self
+            .http
+            .client()?
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .header("Content-Type", &mimetype)
+            .body(data)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let response = response
+            .json::<serde_json::Value>()
+            .await
+            .map_err(|e| format!("Failed to parse the Matrix response ({}): {}", status, e))?;
+        let content_uri = response
+            .get("content_uri")
+            .and_then(|v| v.as_str())
+            .ok_or(format!("Uploading responded with {}: {}", status, response))?;
+
+        let content = serde_json::json!({
+            "msgtype": "m.image",
+            "body": filename,
+            "url": content_uri,
+            "info": { "mimetype": mimetype, "size": size },
+        });
+        self.send_event("m.room.message", &content).await?;
+        Ok(())
+    }
+
+    /// Build the URL of an endpoint of the homeserver
+    fn url(&self, segments: &[&str]) -> Result<reqwest::Url, String> {
+        let mut url = reqwest::Url::parse(&self.homeserver)
+            .map_err(|e| "Failed to parse the homeserver: ".to_string() + &e.to_string())?;
+        url.path_segments_mut()
+            .map_err(|_| "The homeserver can not be used as a base URL".to_string())?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// Send an event into the room, returning the event id
+    async fn send_event(
+        &self,
+        event_type: &str,
+        content: &serde_json::Value,
+    ) -> Result<String, String> {
+        let transaction_id = format!(
+            "rwt{}.{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_err(|e| e.to_string())?
+                .as_millis(),
+            TRANSACTION_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let url = self.url(&[
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            &self.room_id,
+            "send",
+            event_type,
+            &transaction_id,
+        ])?;
+        debug!(
+            "Submitting event to Matrix room {} (via {}): {:#?}",
+            self.room_id, self.homeserver, content
+        );
+
+        let client = self.http.client()?;
+        let body = serde_json::to_string(content).map_err(|e| e.to_string())?;
+        let mut retries = 0;
+        loop {
+            // Using the same transaction id for retries, so the homeserver can deduplicate them
+            let response = client
+                .put(url.clone())
+                .bearer_auth(&self.access_token)
+                .header("Content-Type", "application/json")
+                .body(body.clone())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let status = response.status();
+            let response = response
+                .json::<serde_json::Value>()
+                .await
+                .map_err(|e| format!("Failed to parse the Matrix response ({}): {}", status, e))?;
+            if status.is_success() {
+                return response
+                    .get("event_id")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string())
+                    .ok_or("The Matrix response does not contain an event_id".to_string());
+            }
+
+            let errcode = response
+                .get("errcode")
+                .and_then(|v| v.as_str())
+                .unwrap_or("M_UNKNOWN");
+            if errcode == "M_LIMIT_EXCEEDED" && retries < self.max_retries.unwrap_or(3) {
+                let retry_after_ms = response
+                    .get("retry_after_ms")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(1000);
+                warn!(
+                    "Rate-limited by the Matrix homeserver, retrying in {} ms",
+                    retry_after_ms
+                );
+                tokio::time::sleep(std::time::Duration::from_millis(retry_after_ms)).await;
+                retries += 1;
+                continue;
+            }
+            return Err(format!(
+                "Matrix homeserver responded with {} ({}): {}",
+                status,
+                errcode,
+                response
+                    .get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("no error message")
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Span};
+
+    fn allowed(images: &MatrixImages, image: &str, link: Option<&str>) -> bool {
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.url = link.map(|link| link.to_string());
+        images.is_allowed(&reqwest::Url::parse(image).unwrap(), &message)
+    }
+
+    #[test]
+    fn images_are_only_fetched_from_the_host_of_the_link() {
+        let images = MatrixImages::default();
+        let grafana = Some("https://grafana.example.com/d/abc?orgId=1");
+        assert!(allowed(
+            &images,
+            "https://grafana.example.com/render/1.png",
+            grafana
+        ));
+        assert!(allowed(
+            &images,
+            "https://GRAFANA.example.com:443/1.png",
+            grafana
+        ));
+        for image in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://localhost:8080/admin",
+            "https://grafana.example.com.evil.com/1.png",
+            "https://grafana.example.com:8443/1.png",
+            "file:///etc/passwd",
+        ] {
+            assert!(!allowed(&images, image, grafana), "{}", image);
+        }
+        assert!(!allowed(&images, "https://grafana.example.com/1.png", None));
+    }
+
+    #[test]
+    fn allowed_hosts_replace_the_default() {
+        let images = MatrixImages {
+            allowed_hosts: Some(vec!["images.example.com".to_string()]),
+            ..Default::default()
+        };
+        let grafana = Some("https://grafana.example.com/d/abc");
+        assert!(allowed(
+            &images,
+            "https://images.example.com/1.png",
+            grafana
+        ));
+        assert!(allowed(
+            &images,
+            "http://images.example.com:8080/1.png",
+            None
+        ));
+        assert!(!allowed(
+            &images,
+            "https://grafana.example.com/1.png",
+            grafana
+        ));
+    }
+
+    /// Serve the same response to all requests, returning the port and the number of requests
+    fn serve(response: String) -> (u16, Arc<AtomicU64>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(AtomicU64::new(0));
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.read(&mut [0; 4096]);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        (port, requests)
+    }
+
+    fn redirect(location: &str) -> String {
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        )
+    }
+
+    #[actix_web::test]
+    async fn redirects_to_other_hosts_are_not_followed() {
+        let (internal, internal_requests) =
+            serve("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_string());
+        let (grafana, _) = serve(redirect(&format!("http://localhost:{}/secret", internal)));
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.url = Some(format!("http://127.0.0.1:{}/d/abc", grafana));
+
+        let image = format!("http://127.0.0.1:{}/render/1.png", grafana);
+        let error = MatrixImages::default()
+            .fetch(&image, &message)
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("Not allowed"), "{}", error);
+        assert_eq!(internal_requests.load(Ordering::SeqCst), 0);
+
+        // Unless the target is allowed as well
+        let images = MatrixImages {
+            allowed_hosts: Some(vec!["127.0.0.1".to_string(), "localhost".to_string()]),
+            ..Default::default()
+        };
+        let response = images.fetch(&image, &message).await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(internal_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn redirect_loops_are_stopped() {
+        let (port, requests) = serve(redirect("/again"));
+        let images = MatrixImages {
+            allowed_hosts: Some(vec!["127.0.0.1".to_string()]),
+            ..Default::default()
+        };
+        let message = Message::preformatted(Status::Firing, "");
+        let error = images
+            .fetch(&format!("http://127.0.0.1:{}/1.png", port), &message)
+            .await
+            .unwrap_err();
+        assert_eq!(error, format!("More than {} redirects", MAX_REDIRECTS));
+        assert_eq!(requests.load(Ordering::SeqCst), MAX_REDIRECTS as u64 + 1);
+    }
+
+    fn sink(on_resolve: &str) -> MatrixSink {
+        serde_json::from_value(serde_json::json!({
+            "homeserver": "https://matrix.example.com",
+            "access_token": "syt_secret",
+            "room_id": "!room:example.com",
+            "on_resolve": on_resolve,
+        }))
+        .unwrap()
+    }
+
+    fn alerts(alerts: &[(Status, &str)]) -> Message {
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.entries = alerts
+            .iter()
+            .map(|(status, fingerprint)| {
+                let mut entry = Entry::new(*status, vec![Span::bold(fingerprint)]);
+                entry.fingerprint = Some(fingerprint.to_string());
+                entry
+            })
+            .collect();
+        message
+    }
+
+    #[test]
+    fn resolutions_are_threaded() {
+        let sink = sink("Thread");
+        sink.remember(&alerts(&[(Status::Firing, "a")]), "$firing")
+            .unwrap();
+        let content = sink
+            .related_content(&alerts(&[(Status::Resolved, "a")]))
+            .unwrap();
+        assert_eq!(
+            content["m.relates_to"],
+            serde_json::json!({
+                "rel_type": "m.thread",
+                "event_id": "$firing",
+                "is_falling_back": true,
+                "m.in_reply_to": { "event_id": "$firing" },
+            })
+        );
+        assert_eq!(content["msgtype"], "m.notice");
+
+        // Unknown alerts and firing ones are posted as new messages
+        for message in [
+            alerts(&[(Status::Resolved, "b")]),
+            alerts(&[(Status::Firing, "a")]),
+            alerts(&[(Status::Resolved, "a"), (Status::Firing, "c")]),
+        ] {
+            let content = sink.related_content(&message).unwrap();
+            assert_eq!(content.get("m.relates_to"), None);
+        }
+    }
+
+    #[test]
+    fn resolutions_are_edited_once_all_alerts_are_resolved() {
+        let sink = sink("Edit");
+        let firing = alerts(&[(Status::Firing, "a"), (Status::Firing, "b")]);
+        sink.remember(&firing, "$firing").unwrap();
+
+        // "b" is still firing, so the original message must stay
+        let first = alerts(&[(Status::Resolved, "a")]);
+        let content = sink.related_content(&first).unwrap();
+        assert_eq!(content["m.relates_to"]["rel_type"], "m.thread");
+        sink.remember(&first, "$first").unwrap();
+
+        let last = alerts(&[(Status::Resolved, "b")]);
+        let content = sink.related_content(&last).unwrap();
+        assert_eq!(
+            content["m.relates_to"],
+            serde_json::json!({ "rel_type": "m.replace", "event_id": "$firing" })
+        );
+        assert_eq!(content["body"], format!("* {}", last.text()));
+        assert_eq!(content["m.new_content"]["body"], last.text());
+        assert_eq!(content["m.new_content"].get("m.relates_to"), None);
+
+        // All alerts of the message resolved at once
+        sink.remember(&firing, "$again").unwrap();
+        let both = alerts(&[(Status::Resolved, "a"), (Status::Resolved, "b")]);
+        let content = sink.related_content(&both).unwrap();
+        assert_eq!(content["m.relates_to"]["event_id"], "$again");
+        assert_eq!(content["m.relates_to"]["rel_type"], "m.replace");
+    }
+
+    #[test]
+    fn resolved_and_expired_messages_are_forgotten() {
+        let mut sink = sink("Thread");
+        sink.remember_for = Some(1);
+        sink.remember(&alerts(&[(Status::Firing, "a")]), "$a")
+            .unwrap();
+        sink.remember(&alerts(&[(Status::Resolved, "a")]), "$resolved")
+            .unwrap();
+        assert!(sink.events.lock().unwrap().is_empty());
+
+        let expired = Instant::now() - sink.remember_for() - Duration::from_secs(1);
+        sink.events
+            .lock()
+            .unwrap()
+            .insert("old".to_string(), ("$old".to_string(), expired));
+        let content = sink
+            .related_content(&alerts(&[(Status::Resolved, "old")]))
+            .unwrap();
+        assert_eq!(content.get("m.relates_to"), None);
+        sink.remember(&alerts(&[(Status::Firing, "new")]), "$new")
+            .unwrap();
+        let events = sink.events.lock().unwrap();
+        assert_eq!(events.keys().collect::<Vec<_>>(), vec!["new"]);
+    }
+
+    #[test]
+    fn the_oldest_messages_are_forgotten_first() {
+        let sink = sink("Thread");
+        {
+            let mut events = sink.events.lock().unwrap();
+            let now = Instant::now();
+            for index in 0..MAX_REMEMBERED_EVENTS {
+                let sent = now + Duration::from_millis(index as u64);
+                events.insert(format!("f{}", index), (format!("${}", index), sent));
+            }
+        }
+        sink.remember(&alerts(&[(Status::Firing, "new")]), "$new")
+            .unwrap();
+        let events = sink.events.lock().unwrap();
+        assert_eq!(events.len(), MAX_REMEMBERED_EVENTS);
+        assert!(events.contains_key("new"));
+        assert!(!events.contains_key("f0"));
+        assert!(events.contains_key("f1"));
+    }
+
+    #[test]
+    fn nothing_is_remembered_for_new_messages() {
+        let sink = sink("Message");
+        sink.remember(&alerts(&[(Status::Firing, "a")]), "$a")
+            .unwrap();
+        assert!(sink.events.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Message, spans_to_markdown, spans_to_text};
+use crate::sink::LengthLimit;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MattermostSink {
+    uri: String,                // the incoming webhook
+    channel: Option<String>,    // overrides the default channel of the webhook
+    username: Option<String>,   // overrides the default username of the webhook
+    icon_url: Option<String>,   // overrides the default profile picture of the webhook
+    icon_emoji: Option<String>, // overrides the icon_url, e.g. ":rotating_light:"
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl MattermostSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let payload = self.payload(message);
+        debug!("Submitting message to Mattermost: {:#?}", payload);
+        let response = self
+            .http
+            .client()?
+            .post(&self.uri)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Mattermost responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    /// Render the title as text and the entries as attachments, with the overrides of the webhook
+    fn payload(&self, message: &Message) -> serde_json::Value {
+        let mut payload = serde_json::json!({
+            "text": title_markdown(message),
+            "attachments": attachments(message),
+        });
+        for (key, value) in [
+            ("channel", &self.channel),
+            ("username", &self.username),
+            ("icon_url", &self.icon_url),
+            ("icon_emoji", &self.icon_emoji),
+        ] {
+            if let Some(value) = value {
+                payload[key] = value.as_str().into();
+            }
+        }
+        payload
+    }
+}
+
+/// Render the title (or the preformatted body) as Markdown
+pub fn title_markdown(message: &Message) -> String {
+    match &message.body {
+        Some(body) => body.clone(),
+        None if !message.title.is_empty() => format!("#### {}", spans_to_markdown(&message.title)),
+        None => "".to_string(),
+    }
+}
+
+/// Render the entries as (Slack-compatible) attachments, as used by Mattermost and Rocket.Chat
+pub fn attachments(message: &Message) -> Vec<serde_json::Value> {
+    message
+        .entries
+        .iter()
+        .map(|entry| {
+            let lines = entry.markdown_lines();
+            let fields: Vec<serde_json::Value> = entry
+                .labels
+                .iter()
+                .map(|(key, value)| serde_json::json!({ "short": true, "title": key, "value": value }))
+                .collect();
+            let mut attachment = serde_json::json!({
+                "fallback": spans_to_text(&entry.headline_with_icon()),
+                "color": entry.status.color(),
+                "text": lines.join("\n"),
+                "fields": fields,
+            });
+            if let Some(author) = &entry.author {
+                attachment["author_name"] = author.as_str().into();
+            }
+            attachment
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Span, Status};
+
+    fn sink(config: serde_json::Value) -> MattermostSink {
+        let mut value = serde_json::json!({ "uri": "https://mattermost.example.com/hooks/xxx" });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(config.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn overrides_are_only_set_if_configured() {
+        let message = Message::preformatted(Status::Firing, "Disk full");
+        let payload = sink(serde_json::json!({})).payload(&message);
+        assert_eq!(
+            payload,
+            serde_json::json!({ "text": "Disk full", "attachments": [] })
+        );
+
+        let sink = sink(serde_json::json!({
+            "channel": "alerts",
+            "username": "Grafana",
+            "icon_url": "https://e.com/grafana.png",
+            "icon_emoji": ":rotating_light:",
+        }));
+        let payload = sink.payload(&message);
+        assert_eq!(payload["channel"], "alerts");
+        assert_eq!(payload["username"], "Grafana");
+        assert_eq!(payload["icon_url"], "https://e.com/grafana.png");
+        assert_eq!(payload["icon_emoji"], ":rotating_light:");
+    }
+
+    #[test]
+    fn entries_are_attachments() {
+        let mut entry = Entry::new(Status::Resolved, vec![Span::bold("HighCPU")]);
+        entry
+            .labels
+            .insert("instance".to_string(), "db1".to_string());
+        entry.author = Some("Jane".to_string());
+        let mut message = Message::preformatted(Status::Resolved, "");
+        message.body = None;
+        message.title = vec![Span::plain("Grafana")];
+        message.entries = vec![entry];
+
+        let payload = sink(serde_json::json!({})).payload(&message);
+        assert_eq!(payload["text"], "#### Grafana");
+        let attachment = &payload["attachments"][0];
+        assert_eq!(attachment["fallback"], "🟢 HighCPU");
+        assert_eq!(attachment["color"], Status::Resolved.color());
+        assert_eq!(attachment["author_name"], "Jane");
+        assert_eq!(
+            attachment["fields"],
+            serde_json::json!([{ "short": true, "title": "instance", "value": "db1" }])
+        );
+    }
+}
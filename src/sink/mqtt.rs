@@ -0,0 +1,147 @@
+use crate::message::Message;
+use crate::sink::JsonPayload;
+use crate::template;
+use log::{debug, warn};
+use rumqttc::{AsyncClient, Event, Incoming, MqttOptions, Outgoing, QoS, Transport};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, mpsc};
+
+/// Counter to make the default client ids unique within this process
+static CLIENT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttSink {
+    host: String,
+    port: Option<u16>,         // (default: 1883, or 8883 with TLS)
+    tls: Option<bool>,         // (default: false)
+    client_id: Option<String>, // (default: "rust-webhook-transformer-<unique suffix>") must be unique per broker
+    username: Option<String>,
+    password: Option<String>,
+    topic: String,                // template, e.g. "alerts/{{status}}"
+    qos: Option<u8>,              // (default: 1)
+    retain: Option<bool>,         // (default: false)
+    payload: Option<JsonPayload>, // (default: Event)
+    timeout: Option<u64>,         // (default: 10) in seconds
+    #[serde(skip)]
+    connection: Arc<Mutex<Option<MqttConnection>>>, // shared by all clones of this config
+}
+
+/// The client of the connection, which is kept open (and reconnected) by a background task
+#[derive(Debug)]
+struct MqttConnection {
+    client: AsyncClient,
+    delivered: mpsc::UnboundedReceiver<()>, // a message was acknowledged (depending on the QoS)
+}
+
+impl MqttSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        // The values must not add topic levels or wildcards
+        let topic = template::render_with(&self.topic, &template::context(message), topic_value);
+        let payload = self.payload.unwrap_or(JsonPayload::Event).render(message)?;
+        let qos = match self.qos.unwrap_or(1) {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            other => return Err(format!("Invalid MQTT QoS: {}", other)),
+        };
+
+        // The messages are published one after another, so the next delivery is the one of this message
+        let mut connection = self.connection.lock().await;
+        let connection = connection.get_or_insert_with(|| self.connect(qos));
+        while connection.delivered.try_recv().is_ok() {} // e.g. of a message, which timed out
+        debug!("Publishing message to MQTT topic {}", topic);
+        connection
+            .client
+            .publish(&topic, qos, self.retain.unwrap_or(false), payload)
+            .await
+            .map_err(|e| e.to_string())?;
+        let timeout = Duration::from_secs(self.timeout.unwrap_or(10));
+        match tokio::time::timeout(timeout, connection.delivered.recv()).await {
+            Ok(Some(())) => Ok(()),
+            Ok(None) => Err("The MQTT connection was closed".to_string()),
+            Err(_) => Err("Timed out while publishing to MQTT".to_string()),
+        }
+    }
+
+    /// Create the client and drive its connection in the background, reconnecting after errors
+    fn connect(&self, qos: QoS) -> MqttConnection {
+        let tls = self.tls.unwrap_or(false);
+        let client_id = self.client_id.clone().unwrap_or_else(|| {
+            // Replicas of this service (or several sinks) must not take over each other's session
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default();
+            let counter = CLIENT_COUNTER.fetch_add(1, Ordering::Relaxed);
+            format!("rust-webhook-transformer-{:x}-{}", nanos, counter)
+        });
+        let mut options = MqttOptions::new(
+            client_id,
+            &self.host,
+            self.port.unwrap_or(if tls { 8883 } else { 1883 }),
+        );
+        if tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        if let Some(username) = &self.username {
+            options.set_credentials(username, self.password.clone().unwrap_or_default());
+        }
+        let (client, mut eventloop) = AsyncClient::new(options, 10);
+        let (sender, delivered) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let result = match eventloop.poll().await {
+                    Ok(Event::Outgoing(Outgoing::Publish(_))) if qos == QoS::AtMostOnce => {
+                        sender.send(())
+                    }
+                    Ok(Event::Incoming(Incoming::PubAck(_))) if qos == QoS::AtLeastOnce => {
+                        sender.send(())
+                    }
+                    Ok(Event::Incoming(Incoming::PubComp(_))) if qos == QoS::ExactlyOnce => {
+                        sender.send(())
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => {
+                        // The next poll reconnects, the unacknowledged messages are sent again
+                        warn!("MQTT connection failed: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        Ok(())
+                    }
+                };
+                if result.is_err() {
+                    break; // the sink is gone
+                }
+            }
+        });
+        MqttConnection { client, delivered }
+    }
+}
+
+/// Replace the characters, which have a special meaning in topics (levels and wildcards)
+fn topic_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '+' | '#' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_values_cannot_add_levels_or_wildcards() {
+        let context = serde_json::json!({ "team": "a/b +#c", "status": "firing" });
+        assert_eq!(
+            template::render_with("alerts/{{status}}/{{team}}", &context, topic_value),
+            "alerts/firing/a_b___c"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use crate::message::Message;
+use crate::sink::JsonPayload;
+use crate::template;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsSink {
+    uri: String,              // e.g. "nats://nats.example.com:4222", or "tls://..."
+    token: Option<String>,    // either a token...
+    username: Option<String>, // ...or username and password
+    password: Option<String>,
+    subject: String,              // template, e.g. "alerts.{{severity}}"
+    payload: Option<JsonPayload>, // (default: Event)
+    timeout: Option<u64>,         // (default: 10) in seconds
+    #[serde(skip)]
+    client: Arc<OnceCell<async_nats::Client>>, // shared by all clones of this config, reconnects by itself
+}
+impl NatsSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        // The values must not add tokens or wildcards
+        let subject =
+            template::render_with(&self.subject, &template::context(message), subject_value);
+        let payload = self.payload.unwrap_or(JsonPayload::Event).render(message)?;
+        let client = self.client.get_or_try_init(|| self.connect()).await?;
+        debug!("Publishing message to NATS subject {}", subject);
+        client
+            .publish(subject, payload.into())
+            .await
+            .map_err(|e| e.to_string())?;
+        client.flush().await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    async fn connect(&self) -> Result<async_nats::Client, String> {
+        let timeout = Duration::from_secs(self.timeout.unwrap_or(10));
+        let mut options = async_nats::ConnectOptions::new()
+            .connection_timeout(timeout)
+            .request_timeout(Some(timeout));
+        if let Some(token) = &self.token {
+            options = options.token(token.clone());
+        }
+        if let Some(username) = &self.username {
+            options = options
+                .user_and_password(username.clone(), self.password.clone().unwrap_or_default());
+        }
+        options.connect(&self.uri).await.map_err(|e| e.to_string())
+    }
+}
+
+/// Replace the characters, which have a special meaning in subjects (tokens and wildcards)
+fn subject_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '.' | '*' | '>' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subject_values_cannot_add_tokens_or_wildcards() {
+        let context = serde_json::json!({ "team": "a.b *>c", "status": "firing" });
+        assert_eq!(
+            template::render_with("alerts.{{status}}.{{team}}", &context, subject_value),
+            "alerts.firing.a_b___c"
+        );
+    }
+}
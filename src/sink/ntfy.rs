@@ -0,0 +1,177 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Message, Status, spans_to_text};
+use crate::sink::LengthLimit;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+const MAX_ACTIONS: usize = 3; // limit of ntfy
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NtfySink {
+    uri: Option<String>,       // (default: "https://ntfy.sh") the server
+    topic: String,             // e.g. "alerts"
+    token: Option<String>,     // access token, if the topic is protected
+    priority: Option<u8>,      // overrides the priority derived from the status (1-5)
+    tags: Option<Vec<String>>, // e.g. ["grafana"], emoji short codes are shown as icons
+    click: Option<String>,     // overrides the URL opened when clicking on the notification
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl NtfySink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let payload = self.payload(message);
+        debug!("Submitting message to ntfy: {:#?}", payload);
+        let mut request = self
+            .http
+            .client()?
+            .post(self.uri.as_deref().unwrap_or("https://ntfy.sh"))
+            .json(&payload);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("ntfy responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    fn payload(&self, message: &Message) -> serde_json::Value {
+        let links = links(message);
+        let mut payload = serde_json::json!({
+            "topic": self.topic,
+            "message": body_markdown(message),
+            "markdown": true,
+            "priority": self.priority.unwrap_or(match message.status {
+                Status::Firing => 4,   // high
+                Status::Pending => 3,  // default
+                Status::Resolved => 2, // low
+                Status::Unknown | Status::Info => 3,
+            }),
+            "tags": self.tags.clone().unwrap_or_default(),
+            "actions": links
+                .iter()
+                .take(MAX_ACTIONS)
+                .map(|(label, url)| serde_json::json!({ "action": "view", "label": label, "url": url }))
+                .collect::<Vec<serde_json::Value>>(),
+        });
+        if !message.title.is_empty() {
+            payload["title"] = spans_to_text(&message.title).into();
+        }
+        if let Some(click) = self.click.as_ref().or(links.first().map(|(_, url)| url)) {
+            payload["click"] = click.as_str().into();
+        }
+        payload
+    }
+}
+
+/// Render the entries (or the preformatted body) as Markdown, as the title is shown separately
+pub fn body_markdown(message: &Message) -> String {
+    let without_title = Message {
+        title: Vec::new(),
+        ..message.clone()
+    };
+    let markdown = without_title.markdown();
+    if markdown.is_empty() {
+        // The message must not be empty
+        return spans_to_text(&message.title);
+    }
+    markdown
+}
+
+/// Collect the links of the title and the entries (without duplicates), to be shown as buttons
+pub fn links(message: &Message) -> Vec<(String, String)> {
+    let mut links: Vec<(String, String)> = Vec::new();
+    let title_links = message
+        .title
+        .iter()
+        .filter_map(|span| Some((span.text.trim().to_string(), span.url.clone()?)));
+    let entry_links = message
+        .entries
+        .iter()
+        .flat_map(|entry| entry.links.iter())
+        .map(|link| (link.label.clone(), link.url.clone()));
+    for (label, url) in title_links.chain(entry_links) {
+        if !links.iter().any(|(_, known)| *known == url) {
+            links.push((label, url));
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Link, Span};
+
+    fn sink(config: serde_json::Value) -> NtfySink {
+        let mut value = serde_json::json!({ "topic": "alerts" });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(config.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn link(url: &str) -> Link {
+        Link {
+            label: "Panel".to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn priority_is_derived_from_the_status() {
+        for (status, expected) in [
+            (Status::Firing, 4),
+            (Status::Pending, 3),
+            (Status::Resolved, 2),
+            (Status::Unknown, 3),
+            (Status::Info, 3),
+        ] {
+            let payload =
+                sink(serde_json::json!({})).payload(&Message::preformatted(status, "Disk full"));
+            assert_eq!(payload["priority"], expected, "{:?}", status);
+        }
+        let payload = sink(serde_json::json!({ "priority": 5 }))
+            .payload(&Message::preformatted(Status::Resolved, "Fine"));
+        assert_eq!(payload["priority"], 5);
+    }
+
+    #[test]
+    fn links_become_actions() {
+        let mut first = Entry::new(Status::Firing, vec![Span::bold("HighCPU")]);
+        first.links = vec![link("https://e.com/1"), link("https://e.com/2")];
+        let mut second = Entry::new(Status::Firing, vec![Span::bold("HighMemory")]);
+        second.links = vec![link("https://e.com/1"), link("https://e.com/3")];
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.title = vec![Span::plain("Grafana").link("https://e.com/0")];
+        message.entries = vec![first, second];
+
+        let payload = sink(serde_json::json!({})).payload(&message);
+        assert_eq!(payload["title"], "Grafana");
+        assert_eq!(payload["click"], "https://e.com/0");
+        let urls: Vec<&str> = payload["actions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|action| action["url"].as_str().unwrap())
+            .collect();
+        // Without duplicates and limited to MAX_ACTIONS
+        assert_eq!(
+            urls,
+            ["https://e.com/0", "https://e.com/1", "https://e.com/2"]
+        );
+
+        let payload =
+            sink(serde_json::json!({ "click": "https://e.com/alerts" })).payload(&message);
+        assert_eq!(payload["click"], "https://e.com/alerts");
+    }
+}
@@ -0,0 +1,223 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Message, Status, spans_to_text};
+use crate::sink::pagerduty::dedup_key;
+use crate::sink::truncate;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+const MAX_MESSAGE_LENGTH: usize = 130;
+const MAX_ALIAS_LENGTH: usize = 512;
+const MAX_DESCRIPTION_LENGTH: usize = 15000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpsgenieSink {
+    api_key: String,                   // of an API integration
+    api_url: Option<String>,           // (default: "https://api.opsgenie.com")
+    dedup_labels: Option<Vec<String>>, // (default: the fingerprint) labels identifying an alert
+    priority: Option<String>,          // (default: derived from the "severity" label, or "P3")
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl OpsgenieSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        for (url, payload) in self.requests(message)? {
+            self.send(&url, &payload).await?;
+        }
+        Ok(())
+    }
+
+    /// Translate the firing and resolved alerts into requests to create and close alerts (URL and payload)
+    fn requests(&self, message: &Message) -> Result<Vec<(String, serde_json::Value)>, String> {
+        let api_url = self
+            .api_url
+            .as_deref()
+            .unwrap_or("https://api.opsgenie.com")
+            .trim_end_matches('/');
+        if message.entries.is_empty() {
+            // Preformatted messages have no alerts, so they can only be created (without deduplication)
+            if message.status != Status::Firing {
+                warn!("Not sending a message without alerts to Opsgenie, as it is not firing");
+                return Ok(Vec::new());
+            }
+            let text = message.text();
+            let payload = serde_json::json!({
+                "message": truncate(text.lines().next().unwrap_or_default(), MAX_MESSAGE_LENGTH),
+                "description": truncate(&text, MAX_DESCRIPTION_LENGTH),
+                "priority": self.priority.as_deref().unwrap_or("P3"),
+                "tags": self.tags.clone().unwrap_or_default(),
+            });
+            return Ok(vec![(format!("{}/v2/alerts", api_url), payload)]);
+        }
+
+        let mut requests = Vec::new();
+        for entry in &message.entries {
+            if !matches!(entry.status, Status::Firing | Status::Resolved) {
+                continue; // e.g. pending alerts do not page (yet)
+            }
+            let Some(alias) = dedup_key(entry, &self.dedup_labels, MAX_ALIAS_LENGTH) else {
+                warn!("Skipping an alert without dedup key for Opsgenie");
+                continue;
+            };
+            if entry.status == Status::Resolved {
+                let mut url = reqwest::Url::parse(api_url).map_err(|e| e.to_string())?;
+                url.path_segments_mut()
+                    .map_err(|_| "The api_url can not be a base".to_string())?
+                    .pop_if_empty()
+                    .extend(["v2", "alerts", &alias, "close"]);
+                url.query_pairs_mut().append_pair("identifierType", "alias");
+                let payload = serde_json::json!({ "source": "rust-webhook-transformer" });
+                requests.push((url.to_string(), payload));
+                continue;
+            }
+
+            let priority = self.priority.clone().unwrap_or(
+                match entry
+                    .labels
+                    .get("severity")
+                    .map(|severity| severity.as_str())
+                {
+                    Some("critical") => "P1",
+                    Some("error" | "high") => "P2",
+                    Some("low") => "P4",
+                    Some("info") => "P5",
+                    _ => "P3",
+                }
+                .to_string(),
+            );
+            let mut description: Vec<String> = entry
+                .details
+                .iter()
+                .map(|detail| spans_to_text(detail))
+                .collect();
+            description.extend(
+                entry
+                    .links
+                    .iter()
+                    .map(|link| format!("{}: {}", link.label, link.url)),
+            );
+            let source = entry
+                .labels
+                .get("instance")
+                .map(|instance| instance.as_str())
+                .unwrap_or("rust-webhook-transformer");
+            let payload = serde_json::json!({
+                "message": truncate(&spans_to_text(&entry.headline), MAX_MESSAGE_LENGTH),
+                "alias": alias,
+                "description": truncate(&description.join("\n"), MAX_DESCRIPTION_LENGTH),
+                "details": entry.labels,
+                "priority": priority,
+                "source": source,
+                "tags": self.tags.clone().unwrap_or_default(),
+            });
+            requests.push((format!("{}/v2/alerts", api_url), payload));
+        }
+        Ok(requests)
+    }
+
+    async fn send(&self, url: &str, payload: &serde_json::Value) -> Result<(), String> {
+        debug!("Submitting request to Opsgenie ({}): {:#?}", url, payload);
+        let response = self
+            .http
+            .client()?
+            .post(url)
+            .header("Authorization", format!("GenieKey {}", self.api_key))
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Opsgenie responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Span};
+
+    fn alert(status: Status, fingerprint: &str, severity: Option<&str>) -> Entry {
+        let mut entry = Entry::new(status, vec![Span::bold("HighCPU")]);
+        entry.fingerprint = Some(fingerprint.to_string());
+        if let Some(severity) = severity {
+            entry
+                .labels
+                .insert("severity".to_string(), severity.to_string());
+        }
+        entry
+    }
+
+    fn requests(
+        config: serde_json::Value,
+        entries: Vec<Entry>,
+    ) -> Vec<(String, serde_json::Value)> {
+        let mut value = serde_json::json!({ "api_key": "K3Y" });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(config.as_object().unwrap().clone());
+        let sink: OpsgenieSink = serde_json::from_value(value).unwrap();
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.entries = entries;
+        sink.requests(&message).unwrap()
+    }
+
+    #[test]
+    fn alerts_are_created_and_closed_by_alias() {
+        let requests = requests(
+            serde_json::json!({ "api_url": "https://api.eu.opsgenie.com/" }),
+            vec![
+                alert(Status::Firing, "f1", None),
+                alert(Status::Resolved, "a/b c", None),
+                alert(Status::Pending, "f3", None),
+            ],
+        );
+        assert_eq!(requests.len(), 2); // pending alerts do not page
+        assert_eq!(requests[0].0, "https://api.eu.opsgenie.com/v2/alerts");
+        assert_eq!(requests[0].1["alias"], "f1");
+        assert_eq!(
+            requests[1].0,
+            "https://api.eu.opsgenie.com/v2/alerts/a%2Fb%20c/close?identifierType=alias"
+        );
+    }
+
+    #[test]
+    fn priority_is_derived_from_the_severity() {
+        let priority = |config: serde_json::Value, severity: Option<&str>| {
+            let requests = requests(config, vec![alert(Status::Firing, "f", severity)]);
+            requests[0].1["priority"].as_str().unwrap().to_string()
+        };
+        for (severity, expected) in [
+            (Some("critical"), "P1"),
+            (Some("error"), "P2"),
+            (Some("high"), "P2"),
+            (Some("warning"), "P3"),
+            (Some("low"), "P4"),
+            (Some("info"), "P5"),
+            (None, "P3"),
+        ] {
+            assert_eq!(priority(serde_json::json!({}), severity), expected);
+        }
+        let fixed = serde_json::json!({ "priority": "P2" });
+        assert_eq!(priority(fixed, Some("info")), "P2");
+    }
+
+    #[test]
+    fn alerts_without_any_dedup_label_keep_their_own_alias() {
+        let config = serde_json::json!({ "dedup_labels": ["service"] });
+        let requests = requests(
+            config,
+            vec![
+                alert(Status::Firing, "f1", None),
+                alert(Status::Firing, "f2", None),
+            ],
+        );
+        assert_eq!(requests[0].1["alias"], "f1");
+        assert_eq!(requests[1].1["alias"], "f2");
+    }
+}
@@ -0,0 +1,280 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Entry, Message, Status, spans_to_text};
+use crate::sink::truncate;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+const MAX_SUMMARY_LENGTH: usize = 1024;
+const MAX_DEDUP_KEY_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PagerDutySink {
+    routing_key: String,               // the integration key of the service
+    api_url: Option<String>,           // (default: "https://events.pagerduty.com")
+    dedup_labels: Option<Vec<String>>, // (default: the fingerprint) labels identifying an alert
+    severity: Option<String>,          // (default: the "severity" label, if valid, or "error")
+    source: Option<String>,            // (default: the "instance" label, if any)
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl PagerDutySink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let url = format!(
+            "{}/v2/enqueue",
+            self.api_url
+                .as_deref()
+                .unwrap_or("https://events.pagerduty.com")
+                .trim_end_matches('/')
+        );
+        let client = self.http.client()?;
+        for event in self.events(message) {
+            debug!("Submitting event to PagerDuty: {:#?}", event);
+            let response = client
+                .post(&url)
+                .json(&event)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                return Err(format!("PagerDuty responded with {}: {}", status, body));
+            }
+        }
+        Ok(())
+    }
+
+    /// Translate the firing and resolved alerts into "trigger" and "resolve" events
+    fn events(&self, message: &Message) -> Vec<serde_json::Value> {
+        if message.entries.is_empty() {
+            // Preformatted messages have no alerts, so they can only be triggered (without deduplication)
+            if message.status != Status::Firing {
+                warn!("Not sending a message without alerts to PagerDuty, as it is not firing");
+                return Vec::new();
+            }
+            return vec![serde_json::json!({
+                "routing_key": self.routing_key,
+                "event_action": "trigger",
+                "payload": {
+                    "summary": truncate(&message.text(), MAX_SUMMARY_LENGTH),
+                    "source": self.source.as_deref().unwrap_or("rust-webhook-transformer"),
+                    "severity": self.severity.as_deref().unwrap_or("error"),
+                },
+            })];
+        }
+
+        let mut events = Vec::new();
+        for entry in &message.entries {
+            let action = match entry.status {
+                Status::Firing => "trigger",
+                Status::Resolved => "resolve",
+                _ => continue, // e.g. pending alerts do not page (yet)
+            };
+            let Some(dedup_key) = dedup_key(entry, &self.dedup_labels, MAX_DEDUP_KEY_LENGTH) else {
+                warn!("Skipping an alert without dedup key for PagerDuty");
+                continue;
+            };
+            let severity = self
+                .severity
+                .clone()
+                .or(entry.labels.get("severity").cloned())
+                .filter(|severity| {
+                    matches!(severity.as_str(), "critical" | "error" | "warning" | "info")
+                })
+                .unwrap_or("error".to_string());
+            let source = self
+                .source
+                .clone()
+                .or(entry.labels.get("instance").cloned())
+                .unwrap_or("rust-webhook-transformer".to_string());
+            let mut details = serde_json::json!(entry.labels);
+            if !entry.details.is_empty() {
+                details["details"] = entry
+                    .details
+                    .iter()
+                    .map(|detail| spans_to_text(detail))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+                    .into();
+            }
+            let links: Vec<serde_json::Value> = entry
+                .links
+                .iter()
+                .map(|link| serde_json::json!({ "href": link.url, "text": link.label }))
+                .collect();
+            events.push(serde_json::json!({
+                "routing_key": self.routing_key,
+                "event_action": action,
+                "dedup_key": dedup_key,
+                "payload": {
+                    "summary": truncate(&spans_to_text(&entry.headline), MAX_SUMMARY_LENGTH),
+                    "source": source,
+                    "severity": severity,
+                    "custom_details": details,
+                },
+                "links": links,
+            }));
+        }
+        events
+    }
+}
+
+/// Stable key of the alert, derived from the selected labels (or its fingerprint, if it has none of them), hashed if too long
+pub fn dedup_key(entry: &Entry, labels: &Option<Vec<String>>, max_length: usize) -> Option<String> {
+    let values: Vec<Option<&String>> = labels
+        .iter()
+        .flatten()
+        .map(|label| entry.labels.get(label))
+        .collect();
+    // Without any of the labels, all such alerts would share a single key (and incident)
+    let key = match values.iter().any(|value| value.is_some()) {
+        true => values
+            .iter()
+            .map(|value| value.map(|value| value.as_str()).unwrap_or(""))
+            .collect::<Vec<&str>>()
+            .join("/"),
+        false => entry.fingerprint.clone()?,
+    };
+    if key.len() <= max_length {
+        return Some(key);
+    }
+    // FNV-1a, as it must stay stable across restarts and versions
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    Some(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Link, Span};
+
+    fn alert(status: Status, fingerprint: &str, labels: &[(&str, &str)]) -> Entry {
+        let mut entry = Entry::new(status, vec![Span::bold("HighCPU")]);
+        entry.fingerprint = Some(fingerprint.to_string());
+        for (key, value) in labels {
+            entry.labels.insert(key.to_string(), value.to_string());
+        }
+        entry
+    }
+
+    fn message(entries: Vec<Entry>) -> Message {
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.entries = entries;
+        message
+    }
+
+    fn sink(config: serde_json::Value) -> PagerDutySink {
+        let mut value = serde_json::json!({ "routing_key": "R0UT1NG" });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(config.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn dedup_keys_use_the_labels_or_the_fingerprint() {
+        let labels = Some(vec!["alertname".to_string(), "instance".to_string()]);
+        let entry = alert(
+            Status::Firing,
+            "f1",
+            &[("alertname", "HighCPU"), ("instance", "db1")],
+        );
+        assert_eq!(dedup_key(&entry, &None, 255).as_deref(), Some("f1"));
+        assert_eq!(
+            dedup_key(&entry, &labels, 255).as_deref(),
+            Some("HighCPU/db1")
+        );
+        let partial = alert(Status::Firing, "f2", &[("instance", "db2")]);
+        assert_eq!(dedup_key(&partial, &labels, 255).as_deref(), Some("/db2"));
+        // Alerts without any of the labels must not share a key
+        let first = alert(Status::Firing, "f3", &[("job", "node")]);
+        let second = alert(Status::Firing, "f4", &[]);
+        assert_eq!(dedup_key(&first, &labels, 255).as_deref(), Some("f3"));
+        assert_eq!(dedup_key(&second, &labels, 255).as_deref(), Some("f4"));
+        let mut unknown = second.clone();
+        unknown.fingerprint = None;
+        assert_eq!(dedup_key(&unknown, &labels, 255), None);
+    }
+
+    #[test]
+    fn long_dedup_keys_are_hashed() {
+        let long = "x".repeat(300);
+        let entry = alert(Status::Firing, &long, &[]);
+        let key = dedup_key(&entry, &None, 255).unwrap();
+        assert_eq!(key.len(), 16);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(dedup_key(&entry, &None, 255).unwrap(), key); // stable
+        let other = alert(Status::Firing, &(long.clone() + "y"), &[]);
+        assert_ne!(dedup_key(&other, &None, 255).unwrap(), key);
+        assert_eq!(dedup_key(&entry, &None, 300).unwrap(), long); // fits exactly
+    }
+
+    #[test]
+    fn alerts_are_triggered_and_resolved() {
+        let mut firing = alert(
+            Status::Firing,
+            "f1",
+            &[("severity", "critical"), ("instance", "db1")],
+        );
+        firing.links.push(Link {
+            label: "Runbook".to_string(),
+            url: "https://runbooks.example.com/cpu".to_string(),
+        });
+        let entries = vec![
+            firing,
+            alert(Status::Resolved, "f2", &[("severity", "warning")]),
+            alert(Status::Pending, "f3", &[]),
+        ];
+        let events = sink(serde_json::json!({})).events(&message(entries));
+        assert_eq!(events.len(), 2); // pending alerts do not page
+        assert_eq!(events[0]["event_action"], "trigger");
+        assert_eq!(events[0]["dedup_key"], "f1");
+        assert_eq!(events[0]["routing_key"], "R0UT1NG");
+        assert_eq!(events[0]["payload"]["severity"], "critical");
+        assert_eq!(events[0]["payload"]["source"], "db1");
+        assert_eq!(
+            events[0]["links"][0]["href"],
+            "https://runbooks.example.com/cpu"
+        );
+        assert_eq!(events[1]["event_action"], "resolve");
+        assert_eq!(events[1]["dedup_key"], "f2");
+    }
+
+    #[test]
+    fn severity_is_derived_from_the_label() {
+        let severity = |sink: &PagerDutySink, label: Option<&str>| {
+            let labels: Vec<(&str, &str)> = label.map(|l| ("severity", l)).into_iter().collect();
+            let events = sink.events(&message(vec![alert(Status::Firing, "f", &labels)]));
+            events[0]["payload"]["severity"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let default = sink(serde_json::json!({}));
+        assert_eq!(severity(&default, Some("warning")), "warning");
+        assert_eq!(severity(&default, Some("info")), "info");
+        assert_eq!(severity(&default, Some("disaster")), "error"); // not valid for PagerDuty
+        assert_eq!(severity(&default, None), "error");
+        let fixed = sink(serde_json::json!({ "severity": "critical" }));
+        assert_eq!(severity(&fixed, Some("info")), "critical");
+    }
+
+    #[test]
+    fn messages_without_alerts_are_only_triggered() {
+        let sink = sink(serde_json::json!({}));
+        let events = sink.events(&Message::preformatted(Status::Firing, "Disk full"));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["event_action"], "trigger");
+        assert_eq!(events[0].get("dedup_key"), None);
+        assert!(
+            sink.events(&Message::preformatted(Status::Resolved, "Fine"))
+                .is_empty()
+        );
+    }
+}
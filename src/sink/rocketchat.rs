@@ -0,0 +1,87 @@
+use crate::http::HttpClientConfig;
+use crate::message::Message;
+use crate::sink::LengthLimit;
+use crate::sink::mattermost::{attachments, title_markdown};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RocketChatSink {
+    uri: String,                // the incoming webhook
+    channel: Option<String>,    // overrides the default channel of the webhook, e.g. "#alerts"
+    username: Option<String>,   // overrides the default username ("alias") of the webhook
+    icon_url: Option<String>,   // overrides the default avatar of the webhook
+    icon_emoji: Option<String>, // overrides the icon_url, e.g. ":rotating_light:"
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl RocketChatSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let payload = self.payload(message);
+        debug!("Submitting message to Rocket.Chat: {:#?}", payload);
+        let response = self
+            .http
+            .client()?
+            .post(&self.uri)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Rocket.Chat responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    /// Render the message like for Mattermost, but with the names of the overrides of Rocket.Chat
+    fn payload(&self, message: &Message) -> serde_json::Value {
+        let mut payload = serde_json::json!({
+            "text": title_markdown(message),
+            "attachments": attachments(message),
+        });
+        for (key, value) in [
+            ("channel", &self.channel),
+            ("alias", &self.username),
+            ("avatar", &self.icon_url),
+            ("emoji", &self.icon_emoji),
+        ] {
+            if let Some(value) = value {
+                payload[key] = value.as_str().into();
+            }
+        }
+        payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Status;
+
+    #[test]
+    fn overrides_use_the_names_of_rocket_chat() {
+        let sink: RocketChatSink = serde_json::from_value(serde_json::json!({
+            "uri": "https://chat.example.com/hooks/xxx",
+            "channel": "#alerts",
+            "username": "Grafana",
+            "icon_url": "https://e.com/grafana.png",
+            "icon_emoji": ":rotating_light:",
+        }))
+        .unwrap();
+        let payload = sink.payload(&Message::preformatted(Status::Firing, "Disk full"));
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "text": "Disk full",
+                "attachments": [],
+                "channel": "#alerts",
+                "alias": "Grafana",
+                "avatar": "https://e.com/grafana.png",
+                "emoji": ":rotating_light:",
+            })
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::http::HttpClientConfig;
+use crate::message::{Message, Span, Style, spans_to_text};
+use crate::sink::{LengthLimit, truncate};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+// Limits of the Block Kit, see https://api.slack.com/reference/block-kit/blocks
+const MAX_HEADER_LENGTH: usize = 150;
+const MAX_SECTION_LENGTH: usize = 3000;
+const MAX_ATTACHMENTS: usize = 20; // more are accepted, but Slack discourages it
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlackSink {
+    uri: String, // the incoming webhook, e.g. "https://hooks.slack.com/services/T000/B000/XXXX"
+    channel: Option<String>, // (default: the one of the webhook) only honored by legacy webhooks
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl SlackSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let payload = self.payload(message);
+        debug!("Submitting message to Slack: {:#?}", payload);
+        let response = self
+            .http
+            .client()?
+            .post(&self.uri)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Slack responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    /// Render the message as Block Kit, with one colored attachment per entry
+    fn payload(&self, message: &Message) -> serde_json::Value {
+        let mut blocks = Vec::new();
+        if !message.title.is_empty() {
+            blocks.push(serde_json::json!({
+                "type": "header",
+                "text": {
+                    "type": "plain_text",
+                    "text": truncate(&spans_to_text(&message.title), MAX_HEADER_LENGTH),
+                    "emoji": true,
+                },
+            }));
+            // The header can not contain links, so list them below
+            let links: Vec<String> = message
+                .title
+                .iter()
+                .filter(|span| span.url.is_some())
+                .map(|span| spans_to_mrkdwn(std::slice::from_ref(span)))
+                .collect();
+            if !links.is_empty() {
+                blocks.push(serde_json::json!({
+                    "type": "context",
+                    "elements": [{ "type": "mrkdwn", "text": links.join(" · ") }],
+                }));
+            }
+        }
+        if let Some(body) = &message.body {
+            blocks.push(section(&escape(body)));
+        }
+
+        let mut attachments = Vec::new();
+        for entry in message.entries.iter().take(MAX_ATTACHMENTS) {
+            let mut lines = vec![spans_to_mrkdwn(&entry.headline_with_icon())];
+            for detail in &entry.details {
+                lines.push(spans_to_mrkdwn(detail));
+            }
+            let mut entry_blocks = vec![section(&lines.join("\n"))];
+            if !entry.links.is_empty() {
+                let links: Vec<String> = entry
+                    .links
+                    .iter()
+                    .map(|link| mrkdwn_link(&link.url, &escape(&link.label)))
+                    .collect();
+                entry_blocks.push(serde_json::json!({
+                    "type": "context",
+                    "elements": [{ "type": "mrkdwn", "text": "→ ".to_string() + &links.join(", ") }],
+                }));
+            }
+            attachments.push(serde_json::json!({
+                "color": entry.status.color(),
+                "blocks": entry_blocks,
+            }));
+        }
+        if message.entries.len() > MAX_ATTACHMENTS {
+            blocks.push(serde_json::json!({
+                "type": "context",
+                "elements": [{
+                    "type": "mrkdwn",
+                    "text": message.language.text(
+                        "limit.more",
+                        &serde_json::json!({ "count": message.entries.len() - MAX_ATTACHMENTS }),
+                    ),
+                }],
+            }));
+        }
+
+        let mut payload = serde_json::json!({
+            "text": message.text(), // used for notifications
+            "blocks": blocks,
+            "attachments": attachments,
+        });
+        if let Some(channel) = &self.channel {
+            payload["channel"] = channel.as_str().into();
+        }
+        payload
+    }
+}
+
+fn section(mrkdwn: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "section",
+        "text": { "type": "mrkdwn", "text": truncate(mrkdwn, MAX_SECTION_LENGTH) },
+    })
+}
+
+/// Escape the control characters of Slack's "mrkdwn"
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Render a link as "<url|label>", a "|" within either would end the URL early (the label is already escaped)
+fn mrkdwn_link(url: &str, label: &str) -> String {
+    format!(
+        "<{}|{}>",
+        escape(url).replace('|', "%7C"),
+        label.replace('|', "¦")
+    )
+}
+
+/// Render the spans as Slack's "mrkdwn"
+fn spans_to_mrkdwn(spans: &[Span]) -> String {
+    let mut mrkdwn = String::new();
+    for span in spans {
+        let text = escape(&span.text);
+        let mut span_mrkdwn = match span.style {
+            Style::Plain => text,
+            Style::Bold => format!("*{}*", text),
+            Style::Code => format!("`{}`", text),
+        };
+        if let Some(url) = &span.url {
+            span_mrkdwn = mrkdwn_link(url, &span_mrkdwn);
+        }
+        mrkdwn += &span_mrkdwn;
+    }
+    mrkdwn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Link, Status};
+
+    fn payload(config: serde_json::Value, message: &Message) -> serde_json::Value {
+        let mut value =
+            serde_json::json!({ "uri": "https://hooks.slack.com/services/T000/B000/XXXX" });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(config.as_object().unwrap().clone());
+        let sink: SlackSink = serde_json::from_value(value).unwrap();
+        sink.payload(message)
+    }
+
+    fn message(entries: Vec<Entry>) -> Message {
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.entries = entries;
+        message
+    }
+
+    #[test]
+    fn entries_are_colored_attachments() {
+        let mut entry = Entry::new(Status::Firing, vec![Span::bold("HighCPU")]);
+        entry.details.push(vec![Span::plain("CPU at 95% <b>")]);
+        let mut message = message(vec![entry, Entry::new(Status::Resolved, vec![])]);
+        message.title = vec![Span::plain("Grafana").link("https://grafana.example.com")];
+
+        let payload = payload(serde_json::json!({}), &message);
+        assert_eq!(payload["blocks"][0]["text"]["text"], "Grafana");
+        assert_eq!(
+            payload["blocks"][1]["elements"][0]["text"],
+            "<https://grafana.example.com|Grafana>"
+        );
+        let attachments = payload["attachments"].as_array().unwrap();
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0]["color"], Status::Firing.color());
+        assert_eq!(
+            attachments[0]["blocks"][0]["text"]["text"],
+            "🔴 *HighCPU*\nCPU at 95% &lt;b&gt;"
+        );
+        assert_eq!(attachments[1]["color"], Status::Resolved.color());
+        assert!(payload.get("channel").is_none());
+    }
+
+    #[test]
+    fn attachments_are_limited() {
+        let entries = (0..MAX_ATTACHMENTS + 3)
+            .map(|index| Entry::new(Status::Firing, vec![Span::plain(&index.to_string())]))
+            .collect();
+        let payload = payload(serde_json::json!({}), &message(entries));
+        assert_eq!(
+            payload["attachments"].as_array().unwrap().len(),
+            MAX_ATTACHMENTS
+        );
+        let blocks = payload["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0]["type"], "context");
+        assert_eq!(blocks[0]["elements"][0]["text"], "… and 3 more");
+    }
+
+    #[test]
+    fn link_labels_cannot_break_the_link() {
+        let mut entry = Entry::new(
+            Status::Firing,
+            vec![Span::plain("a|b>c").link("https://e.com/?q=a|b")],
+        );
+        entry.links.push(Link {
+            label: "Panel | CPU > 90%".to_string(),
+            url: "https://e.com/d/abc?var=a|b".to_string(),
+        });
+        let payload = payload(serde_json::json!({}), &message(vec![entry]));
+        let blocks = &payload["attachments"][0]["blocks"];
+        assert_eq!(
+            blocks[0]["text"]["text"],
+            "🔴 <https://e.com/?q=a%7Cb|a¦b&gt;c>"
+        );
+        assert_eq!(
+            blocks[1]["elements"][0]["text"],
+            "→ <https://e.com/d/abc?var=a%7Cb|Panel ¦ CPU &gt; 90%>"
+        );
+    }
+
+    #[test]
+    fn channel_is_only_set_if_configured() {
+        let message = Message::preformatted(Status::Firing, "Disk full");
+        let payload = payload(serde_json::json!({ "channel": "#alerts" }), &message);
+        assert_eq!(payload["channel"], "#alerts");
+        assert_eq!(payload["text"], "Disk full");
+    }
+}
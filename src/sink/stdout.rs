@@ -0,0 +1,22 @@
+use crate::message::Message;
+use crate::sink::JsonPayload;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StdoutSink {
+    payload: Option<JsonPayload>, // (default: Message)
+    pretty: Option<bool>, // (default: false) indent the JSON, instead of one line per message
+}
+impl StdoutSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let value = self.payload.unwrap_or(JsonPayload::Message).value(message);
+        let json = if self.pretty.unwrap_or(false) {
+            serde_json::to_string_pretty(&value)
+        } else {
+            serde_json::to_string(&value)
+        }
+        .map_err(|e| e.to_string())?;
+        println!("{}", json);
+        Ok(())
+    }
+}
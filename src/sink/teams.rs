@@ -0,0 +1,202 @@
+use crate::escape;
+use crate::http::HttpClientConfig;
+use crate::message::{Message, Status, spans_to_markdown};
+use crate::sink::LengthLimit;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamsSink {
+    uri: String, // the "Post to a channel when a webhook request is received" workflow
+    pub(crate) limit: Option<LengthLimit>,
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl TeamsSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let payload = serde_json::json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "contentUrl": null,
+                "content": self.card(message),
+            }],
+        });
+        debug!("Submitting message to Teams: {:#?}", payload);
+        let response = self
+            .http
+            .client()?
+            .post(&self.uri)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Teams responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    /// Render the message as Adaptive Card, with one container (fact set and buttons) per entry
+    fn card(&self, message: &Message) -> serde_json::Value {
+        let mut body = Vec::new();
+        if !message.title.is_empty() {
+            body.push(serde_json::json!({
+                "type": "TextBlock",
+                "text": spans_to_markdown(&message.title),
+                "size": "Large",
+                "weight": "Bolder",
+                "wrap": true,
+            }));
+        }
+        if let Some(text) = &message.body {
+            body.push(serde_json::json!({ "type": "TextBlock", "text": text, "wrap": true }));
+        }
+        for entry in &message.entries {
+            let mut items = vec![serde_json::json!({
+                "type": "TextBlock",
+                "text": spans_to_markdown(&entry.headline_with_icon()),
+                "wrap": true,
+            })];
+            for detail in &entry.details {
+                items.push(serde_json::json!({
+                    "type": "TextBlock",
+                    "text": spans_to_markdown(detail),
+                    "isSubtle": true,
+                    "wrap": true,
+                }));
+            }
+            if !entry.labels.is_empty() {
+                let facts: Vec<serde_json::Value> = entry
+                    .labels
+                    .iter()
+                    .map(|(key, value)| serde_json::json!({ "title": key, "value": value }))
+                    .collect();
+                items.push(serde_json::json!({ "type": "FactSet", "facts": facts }));
+            }
+            // Teams rejects the whole card because of e.g. relative links
+            let actions: Vec<serde_json::Value> = entry
+                .links
+                .iter()
+                .filter_map(|link| {
+                    escape::absolute_url(&link.url).map(|url| {
+                        serde_json::json!({
+                            "type": "Action.OpenUrl",
+                            "title": link.label,
+                            "url": url,
+                        })
+                    })
+                })
+                .collect();
+            if !actions.is_empty() {
+                items.push(serde_json::json!({ "type": "ActionSet", "actions": actions }));
+            }
+            body.push(serde_json::json!({
+                "type": "Container",
+                "style": match entry.status {
+                    Status::Firing => "attention",
+                    Status::Pending => "warning",
+                    Status::Resolved => "good",
+                    Status::Unknown | Status::Info => "default",
+                },
+                "separator": true,
+                "items": items,
+            }));
+        }
+        serde_json::json!({
+            "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+            "type": "AdaptiveCard",
+            "version": "1.4",
+            "msteams": { "width": "Full" },
+            "body": body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Entry, Link, Span};
+
+    fn card(message: &Message) -> serde_json::Value {
+        let sink: TeamsSink =
+            serde_json::from_value(serde_json::json!({ "uri": "https://e.com/workflow" })).unwrap();
+        sink.card(message)
+    }
+
+    fn link(url: &str) -> Link {
+        Link {
+            label: "Panel".to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn entries_are_rendered_as_containers() {
+        let mut entry = Entry::new(Status::Firing, vec![Span::bold("HighCPU")]);
+        entry.details.push(vec![Span::plain("CPU at 95%")]);
+        entry
+            .labels
+            .insert("instance".to_string(), "db1".to_string());
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.title = vec![Span::plain("Grafana")];
+        message.entries = vec![
+            entry,
+            Entry::new(Status::Resolved, vec![Span::plain("Disk")]),
+        ];
+
+        let card = card(&message);
+        let body = card["body"].as_array().unwrap();
+        assert_eq!(body.len(), 3);
+        assert_eq!(body[0]["text"], "Grafana");
+        assert_eq!(body[1]["style"], "attention");
+        assert_eq!(body[1]["items"][1]["text"], "CPU at 95%");
+        assert_eq!(
+            body[1]["items"][2]["facts"],
+            serde_json::json!([{ "title": "instance", "value": "db1" }])
+        );
+        assert_eq!(body[2]["style"], "good");
+        assert_eq!(body[2]["items"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn only_absolute_links_become_buttons() {
+        let mut entry = Entry::new(Status::Firing, vec![Span::bold("HighCPU")]);
+        entry.links = vec![
+            link("/d/abc"),
+            link("javascript:alert(1)"),
+            link("https://grafana.example.com/d/abc"),
+        ];
+        let mut relative = Entry::new(Status::Firing, vec![Span::bold("Disk")]);
+        relative.links = vec![link("/d/def")];
+        let mut message = Message::preformatted(Status::Firing, "");
+        message.body = None;
+        message.entries = vec![entry, relative];
+
+        let card = card(&message);
+        let actions = &card["body"][0]["items"][1]["actions"];
+        assert_eq!(
+            actions,
+            &serde_json::json!([{
+                "type": "Action.OpenUrl",
+                "title": "Panel",
+                "url": "https://grafana.example.com/d/abc",
+            }])
+        );
+        // Without any button left, there is no (empty) action set
+        assert_eq!(card["body"][1]["items"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn preformatted_messages_are_shown_as_text() {
+        let card = card(&Message::preformatted(Status::Firing, "Disk full"));
+        assert_eq!(card["type"], "AdaptiveCard");
+        assert_eq!(
+            card["body"],
+            serde_json::json!([{ "type": "TextBlock", "text": "Disk full", "wrap": true }])
+        );
+    }
+}
@@ -0,0 +1,528 @@
+use crate::escape;
+use crate::http::HttpClientConfig;
+use crate::message::Message;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+const MAX_MESSAGE_LENGTH: usize = 4096; // in UTF-16 code units
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramSink {
+    bot_token: String,
+    chat_id: String,         // numeric id of the chat or "@channelusername"
+    api_url: Option<String>, // (default: "https://api.telegram.org")
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig,
+}
+impl TelegramSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let tokens = match message.html() {
+            Some(html) => sanitize(&html),
+            None => vec![Token::Text(message.text())],
+        };
+        let url = format!(
+            "{}/bot{}/sendMessage",
+            self.api_url
+                .as_deref()
+                .unwrap_or("https://api.telegram.org")
+                .trim_end_matches('/'),
+            self.bot_token
+        );
+        let client = self.http.client()?;
+        // Parts are sent one after another, to keep their order
+        for text in split(&tokens, MAX_MESSAGE_LENGTH) {
+            let payload = serde_json::json!({
+                "chat_id": self.chat_id,
+                "text": text,
+                "parse_mode": "HTML",
+                "link_preview_options": { "is_disabled": true },
+            });
+            debug!("Submitting message to Telegram: {:#?}", payload);
+            // The error must not contain the URL, as the token is part of it
+            let response = client
+                .post(&url)
+                .json(&payload)
+                .send()
+                .await
+                .map_err(|e| e.without_url().to_string())?;
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                return Err(format!("Telegram responded with {}: {}", status, body));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Piece of Telegram-compatible HTML
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open(&'static str, Option<String>), // tag name and (for links) the href
+    Close(&'static str),
+    Text(String), // unescaped
+}
+
+/// Reduce the HTML to the tags supported by Telegram, converting the block tags to line breaks
+fn sanitize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (text, tag) = match rest.find('<') {
+            Some(start) => match rest[start..].find('>') {
+                Some(end) => (&rest[..start], Some(&rest[start + 1..start + end])),
+                None => (rest, None), // not a tag, just a lonely "<"
+            },
+            None => (rest, None),
+        };
+        if !text.is_empty() {
+            tokens.push(Token::Text(unescape(text)));
+        }
+        let Some(tag) = tag else {
+            break;
+        };
+        rest = &rest[text.len() + tag.len() + 2..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        let is_heading = matches!(name.as_str(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6");
+        let name = match name.as_str() {
+            _ if is_heading => "b",
+            "b" | "strong" => "b",
+            "i" | "em" => "i",
+            "code" => "code",
+            "pre" => "pre",
+            "a" => "a",
+            "br" => {
+                tokens.push(Token::Text("\n".to_string()));
+                continue;
+            }
+            "p" => {
+                if closing {
+                    tokens.push(Token::Text("\n\n".to_string()));
+                }
+                continue;
+            }
+            _ => continue, // drop unsupported tags, but keep their text
+        };
+        if closing {
+            tokens.push(Token::Close(name));
+            if is_heading {
+                tokens.push(Token::Text("\n".to_string()));
+            }
+        } else {
+            let href = if name == "a" {
+                let href = attribute(tag, "href").map(|href| unescape(&href));
+                // Telegram rejects the whole message because of e.g. relative links
+                if href.as_deref().and_then(escape::absolute_url).is_none() {
+                    continue; // drop the link (and its unbalanced closing tag), but keep its text
+                }
+                href
+            } else {
+                None
+            };
+            tokens.push(Token::Open(name, href));
+        }
+    }
+    // Remove trailing line breaks
+    while let Some(Token::Text(text)) = tokens.last_mut() {
+        let trimmed = text.trim_end().to_string();
+        if trimmed.is_empty() {
+            tokens.pop();
+        } else {
+            *text = trimmed;
+            break;
+        }
+    }
+    tokens
+}
+
+/// Extract the (quoted) value of an attribute from the inner part of a tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let value = &tag[start..];
+    let quote = value.chars().next()?;
+    if quote == '"' || quote == '\'' {
+        value[1..].split(quote).next().map(|v| v.to_string())
+    } else {
+        value.split_whitespace().next().map(|v| v.to_string())
+    }
+}
+
+/// Decode the named (of the escaped characters) and numeric character references in a single pass
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped += &rest[..start];
+        rest = &rest[start..];
+        let reference = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let decoded = match reference {
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some("quot") => Some('"'),
+            Some("apos") => Some('\''),
+            Some("amp") => Some('&'),
+            Some(number) if number.starts_with("#x") || number.starts_with("#X") => {
+                u32::from_str_radix(&number[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            Some(number) if number.starts_with('#') => {
+                number[1..].parse().ok().and_then(char::from_u32)
+            }
+            _ => None,
+        };
+        match (decoded, reference) {
+            (Some(c), Some(reference)) => {
+                unescaped.push(c);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped += rest;
+    unescaped
+}
+
+fn open_tag(name: &str, href: &Option<String>) -> String {
+    match href {
+        Some(href) => format!("<{} href=\"{}\">", name, escape::html_attribute(href)),
+        None => format!("<{}>", name),
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(|c| c.len_utf16()).sum()
+}
+
+/// Render the tokens as HTML messages of at most `max_length`, preferably splitting at line breaks
+fn split(tokens: &[Token], max_length: usize) -> Vec<String> {
+    // Group the tokens into lines (each ending with its line break)
+    let mut lines = vec![Vec::new()];
+    for token in tokens {
+        match token {
+            Token::Text(text) => {
+                for part in text.split_inclusive('\n') {
+                    if let Some(line) = lines.last_mut() {
+                        line.push(Token::Text(part.to_string()));
+                    }
+                    if part.ends_with('\n') {
+                        lines.push(Vec::new());
+                    }
+                }
+            }
+            other => {
+                if let Some(line) = lines.last_mut() {
+                    line.push(other.clone());
+                }
+            }
+        }
+    }
+
+    // Pack as many lines as possible into each message
+    let mut splitter = Splitter {
+        max_length,
+        messages: Vec::new(),
+        current: String::new(),
+        stack: Vec::new(),
+    };
+    for line in lines {
+        if !splitter.try_push_line(&line) {
+            splitter.flush();
+            if !splitter.try_push_line(&line) {
+                // The line is too long on its own, so it must be split
+                for token in &line {
+                    splitter.push_token(token);
+                }
+            }
+        }
+    }
+    splitter.finish()
+}
+
+struct Splitter {
+    max_length: usize,
+    messages: Vec<String>,
+    current: String,
+    stack: Vec<(&'static str, Option<String>)>, // currently open tags
+}
+impl Splitter {
+    fn closing_length(stack: &[(&'static str, Option<String>)]) -> usize {
+        stack.iter().map(|(name, _)| name.len() + 3).sum()
+    }
+
+    /// Append the whole line, if it fits into the current message
+    fn try_push_line(&mut self, line: &[Token]) -> bool {
+        let mut html = String::new();
+        let mut stack = self.stack.clone();
+        for token in line {
+            match token {
+                Token::Open(name, href) => {
+                    html += &open_tag(name, href);
+                    stack.push((name, href.clone()));
+                }
+                Token::Close(name) => {
+                    // Ignore unbalanced closing tags
+                    if stack.last().map(|(open, _)| open) == Some(name) {
+                        html += &format!("</{}>", name);
+                        stack.pop();
+                    }
+                }
+                Token::Text(text) => html += &escape::html_text(text),
+            }
+        }
+        if utf16_len(&self.current) + utf16_len(&html) + Self::closing_length(&stack)
+            > self.max_length
+        {
+            return false;
+        }
+        self.current += &html;
+        self.stack = stack;
+        true
+    }
+
+    /// Append a single token, splitting the text if it does not fit
+    fn push_token(&mut self, token: &Token) {
+        match token {
+            Token::Open(name, href) => {
+                // Do not start a tag, which does not fit anymore
+                let tag = open_tag(name, href);
+                let fits = |splitter: &Self| {
+                    utf16_len(&splitter.current)
+                        + utf16_len(&tag)
+                        + Self::closing_length(&splitter.stack)
+                        + name.len()
+                        + 3
+                        <= splitter.max_length
+                };
+                if !fits(self) {
+                    self.flush();
+                }
+                if !fits(self) {
+                    return; // e.g. an overlong href, drop the tag (its closing one is ignored) but keep the text
+                }
+                self.current += &tag;
+                self.stack.push((name, href.clone()));
+            }
+            Token::Close(name) => {
+                if self.stack.last().map(|(open, _)| open) == Some(name) {
+                    self.current += &format!("</{}>", name);
+                    self.stack.pop();
+                }
+            }
+            Token::Text(text) => {
+                let mut rest: &str = text;
+                while !rest.is_empty() {
+                    let budget = self.max_length.saturating_sub(
+                        utf16_len(&self.current) + Self::closing_length(&self.stack),
+                    );
+                    // Find the longest prefix, which fits
+                    let mut length = 0;
+                    let mut cut = 0;
+                    for (index, c) in rest.char_indices() {
+                        length += utf16_len(&escape::html_text(&c.to_string()));
+                        if length > budget {
+                            break;
+                        }
+                        cut = index + c.len_utf8();
+                    }
+                    if cut == rest.len() {
+                        self.current += &escape::html_text(rest);
+                        break;
+                    }
+                    if cut == 0 && !self.has_text() {
+                        // Not even a single character fits, so exceed the limit to make progress
+                        cut = rest.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+                    }
+                    self.current += &escape::html_text(&rest[..cut]);
+                    rest = &rest[cut..];
+                    self.flush();
+                }
+            }
+        }
+    }
+
+    fn has_text(&self) -> bool {
+        sanitize(&self.current)
+            .iter()
+            .any(|token| matches!(token, Token::Text(text) if !text.trim().is_empty()))
+    }
+
+    /// Close all open tags, finish the message and reopen the tags for the next one
+    fn flush(&mut self) {
+        for (name, _) in self.stack.iter().rev() {
+            self.current += &format!("</{}>", name);
+        }
+        if self.has_text() {
+            self.messages.push(std::mem::take(&mut self.current));
+        }
+        self.current.clear();
+        for (name, href) in &self.stack {
+            self.current += &open_tag(name, href);
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.flush();
+        self.messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check the limit and that the tags are supported and balanced, returning the unescaped text
+    fn check(parts: &[String], max_length: usize) -> String {
+        let mut text = String::new();
+        for part in parts {
+            assert!(utf16_len(part) <= max_length, "too long: {}", part);
+            let mut stack = Vec::new();
+            let mut rest = part.as_str();
+            while let Some(start) = rest.find('<') {
+                text += &unescape(&rest[..start]);
+                let end = start + rest[start..].find('>').expect("unterminated tag");
+                let tag = &rest[start + 1..end];
+                let name = tag.trim_start_matches('/').split(' ').next().unwrap();
+                assert!(
+                    ["b", "i", "code", "pre", "a"].contains(&name),
+                    "unsupported tag: {}",
+                    tag
+                );
+                if tag.starts_with('/') {
+                    assert_eq!(stack.pop(), Some(name), "unbalanced: {}", part);
+                } else {
+                    stack.push(name);
+                }
+                rest = &rest[end + 1..];
+            }
+            text += &unescape(rest);
+            assert!(stack.is_empty(), "unclosed tags: {}", part);
+        }
+        text
+    }
+
+    #[test]
+    fn only_supported_tags_survive() {
+        let html = "<h4>Heading</h4><p><strong>bold</strong> <em>italic</em> <span>span</span> \
+                    <code>code</code> <pre>pre</pre> <a href=\"https://example.com/?a=1&amp;b=2\">link</a> \
+                    <img src=\"x\"><div>div</div><table><tr><td>cell</td></tr></table></p>";
+        let parts = split(&sanitize(html), MAX_MESSAGE_LENGTH);
+        assert_eq!(
+            parts,
+            vec![
+                "<b>Heading</b>\n<b>bold</b> <i>italic</i> span <code>code</code> <pre>pre</pre> \
+                 <a href=\"https://example.com/?a=1&amp;b=2\">link</a> divcell"
+            ]
+        );
+        check(&parts, MAX_MESSAGE_LENGTH);
+    }
+
+    #[test]
+    fn parts_fit_and_reopen_the_tags() {
+        let line = "<b>alert <i>".to_string() + &"x".repeat(100) + "</i></b><br>";
+        let html = line.repeat(100) + "<b><i>" + &"y".repeat(10000) + "</i></b>";
+        let parts = split(&sanitize(&html), MAX_MESSAGE_LENGTH);
+        assert!(parts.len() > 3);
+        let text = check(&parts, MAX_MESSAGE_LENGTH);
+        assert_eq!(text.matches('x').count(), 10000);
+        assert_eq!(text.matches('y').count(), 10000);
+        // The text continued within the tags starts with them again
+        assert!(parts.last().unwrap().starts_with("<b><i>y"));
+    }
+
+    #[test]
+    fn multi_unit_characters_are_counted() {
+        // Each emoji is two UTF-16 code units, "&" is escaped to five
+        let html = "🚨".repeat(3000) + &"&amp;".repeat(1000);
+        let parts = split(&sanitize(&html), MAX_MESSAGE_LENGTH);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], "🚨".repeat(2048));
+        let text = check(&parts, MAX_MESSAGE_LENGTH);
+        assert_eq!(text, "🚨".repeat(3000) + &"&".repeat(1000));
+    }
+
+    #[test]
+    fn overlong_links_are_dropped() {
+        let href = "https://example.com/".to_string() + &"a".repeat(5000);
+        let html = format!(
+            "<a href=\"{}\">dashboard</a> and <a href=\"https://e.com\">panel</a>",
+            href
+        );
+        let parts = split(&sanitize(&html), MAX_MESSAGE_LENGTH);
+        assert_eq!(
+            parts,
+            vec!["dashboard and <a href=\"https://e.com\">panel</a>"]
+        );
+    }
+
+    #[test]
+    fn escaped_text_is_not_turned_into_tags() {
+        let html = "<p>&lt;script&gt;alert(1)&lt;/script&gt; &lt;b&gt;not bold&lt;/b&gt; \
+                    &lt;a href=&quot;javascript:alert(1)&quot;&gt;x&lt;/a&gt; &amp;lt;</p>";
+        let parts = split(&sanitize(html), MAX_MESSAGE_LENGTH);
+        assert_eq!(
+            parts,
+            vec![
+                "&lt;script&gt;alert(1)&lt;/script&gt; &lt;b&gt;not bold&lt;/b&gt; \
+                 &lt;a href=\"javascript:alert(1)\"&gt;x&lt;/a&gt; &amp;lt;"
+            ]
+        );
+        check(&parts, MAX_MESSAGE_LENGTH);
+    }
+
+    #[test]
+    fn unsafe_links_are_dropped() {
+        for href in [
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            "java&#9;script:alert(1)",
+            "java\tscript:alert(1)",
+            "data:text/html,x",
+        ] {
+            let html = format!(
+                "<a href=\"{}\">link</a> and <a href='https://e.com/d/abc'>panel</a>",
+                href
+            );
+            let parts = split(&sanitize(&html), MAX_MESSAGE_LENGTH);
+            assert_eq!(
+                parts,
+                vec!["link and <a href=\"https://e.com/d/abc\">panel</a>"],
+                "{}",
+                href
+            );
+        }
+    }
+
+    #[test]
+    fn relative_links_are_dropped() {
+        let html = "<a href=\"/d/abc\">panel</a>, <a href=\"mailto:a@e.com\">mail</a>";
+        let parts = split(&sanitize(html), MAX_MESSAGE_LENGTH);
+        assert_eq!(parts, vec!["panel, mail"]);
+    }
+
+    #[test]
+    fn quotes_cannot_break_out_of_hrefs() {
+        let html = "<a href=\"https://e.com/?a=&quot;&gt;&lt;script&gt;\">link</a>";
+        let parts = split(&sanitize(html), MAX_MESSAGE_LENGTH);
+        assert_eq!(
+            parts,
+            vec!["<a href=\"https://e.com/?a=&quot;&gt;&lt;script&gt;\">link</a>"]
+        );
+    }
+
+    #[test]
+    fn a_single_character_is_cut_when_nothing_fits() {
+        let parts = split(&sanitize("<b>🚨🚨🚨</b>"), 8);
+        assert_eq!(parts, vec!["<b>🚨</b>", "<b>🚨</b>", "<b>🚨</b>"]);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::escape;
+use crate::http::HttpClientConfig;
+use crate::message::Message;
+use crate::template;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, LinkedList};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSink {
+    uri: String,               // template
+    method: Option<String>,    // (default: POST)
+    body: Option<WebhookBody>, // (default: all values of the message as JSON)
+    auth: Option<WebhookAuth>,
+    forward_headers: Option<LinkedList<String>>, // names of the incoming headers to pass through
+    #[serde(default)]
+    pub(crate) http: HttpClientConfig, // static headers can be set here
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WebhookBody {
+    Json(serde_json::Value),        // all strings within are templates
+    Form(BTreeMap<String, String>), // form-encoded, the values are templates
+    Text(String),                   // template, sent as "text/plain"
+    Original,                       // the body of the incoming request, with its content type
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WebhookAuth {
+    Basic { username: String, password: String },
+    Bearer(String),
+}
+
+impl WebhookSink {
+    pub async fn submit(&self, message: &Message) -> Result<(), String> {
+        let context = template::context(message);
+        let method = reqwest::Method::from_bytes(
+            self.method
+                .as_deref()
+                .unwrap_or("POST")
+                .to_uppercase()
+                .as_bytes(),
+        )
+        .map_err(|e| e.to_string())?;
+        // The values are percent-encoded, so that e.g. a label cannot change the path or query
+        let uri = template::render_with(&self.uri, &context, escape::url_component);
+        let mut request = self.http.client()?.request(method, &uri);
+
+        request = match &self.body {
+            None => request.json(&template::event(message)),
+            Some(WebhookBody::Json(body)) => request.json(&template::render_json(body, &context)),
+            Some(WebhookBody::Form(body)) => {
+                let form: BTreeMap<&String, String> = body
+                    .iter()
+                    .map(|(key, value)| (key, template::render(value, &context)))
+                    .collect();
+                request.form(&form)
+            }
+            Some(WebhookBody::Text(body)) => request
+                .header("Content-Type", "text/plain; charset=utf-8")
+                .body(template::render(body, &context)),
+            Some(WebhookBody::Original) => {
+                let origin = message
+                    .origin
+                    .as_ref()
+                    .ok_or("The message has no original request".to_string())?;
+                if let Some((_, content_type)) = origin
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name == "content-type")
+                {
+                    request = request.header("Content-Type", content_type);
+                }
+                request.body(origin.body.clone())
+            }
+        };
+        if let Some(origin) = &message.origin {
+            for name in self.forward_headers.iter().flatten() {
+                let name = name.to_lowercase();
+                for (_, value) in origin.headers.iter().filter(|(other, _)| *other == name) {
+                    request = request.header(&name, value);
+                }
+            }
+        }
+        request = match &self.auth {
+            Some(WebhookAuth::Basic { username, password }) => {
+                request.basic_auth(username, Some(password))
+            }
+            Some(WebhookAuth::Bearer(token)) => request.bearer_auth(token),
+            None => request,
+        };
+
+        debug!("Submitting message to {}: {:#?}", uri, request);
+        let response = request
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("The webhook responded with {}: {}", status, body));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use crate::message::{Message, spans_to_text};
+
+/// Values of the message, which can be used in templates (e.g. "{{status}}" or "{{labels.severity}}")
+pub fn context(message: &Message) -> serde_json::Value {
+    // The labels of all entries, the first entry wins
+    let mut labels = serde_json::Map::new();
+    for entry in &message.entries {
+        for (key, value) in &entry.labels {
+            labels.entry(key.clone()).or_insert(value.as_str().into());
+        }
+    }
+    let mut context = serde_json::json!({
+        "status": format!("{:?}", message.status).to_lowercase(),
+        "level": message.status.level(),
+        "title": spans_to_text(&message.title),
+        "text": message.text(),
+        "html": message.html(),
+        "markdown": message.markdown(),
+        "count": message.entries.len(),
+        "entries": message.entries,
+        "labels": labels,
+    });
+    if let Some(origin) = &message.origin {
+        let headers: serde_json::Map<String, serde_json::Value> = origin
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.as_str().into()))
+            .collect();
+        context["origin"] = serde_json::json!({ "body": origin.body, "headers": headers });
+    }
+    // For convenience, the labels are also available directly (e.g. "{{severity}}")
+    if let Some(object) = context.as_object_mut() {
+        for (key, value) in labels {
+            object.entry(key).or_insert(value);
+        }
+    }
+    context
+}
+
+/// The context without the incoming request, as it is only forwarded explicitly
+pub fn event(message: &Message) -> serde_json::Value {
+    let mut context = context(message);
+    if let Some(object) = context.as_object_mut() {
+        object.remove("origin");
+    }
+    context
+}
+
+/// Replace the placeholders (dot-separated paths into the context) of the template, missing values become empty
+pub fn render(template: &str, context: &serde_json::Value) -> String {
+    render_with(template, context, str::to_string)
+}
+
+/// Like `render`, but the values are encoded first (e.g. percent-encoded for URLs)
+pub fn render_with(
+    template: &str,
+    context: &serde_json::Value,
+    encode: impl Fn(&str) -> String,
+) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered += &rest[..start];
+        match lookup(&rest[start + 2..start + end], context) {
+            None | Some(serde_json::Value::Null) => {}
+            Some(serde_json::Value::String(text)) => rendered += &encode(text),
+            Some(other) => rendered += &encode(&other.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered += rest;
+    rendered
+}
+
+/// Render all strings within the JSON template, a string consisting only of one placeholder keeps the type of its value
+pub fn render_json(template: &serde_json::Value, context: &serde_json::Value) -> serde_json::Value {
+    match template {
+        serde_json::Value::String(text) => {
+            let trimmed = text.trim();
+            let single = trimmed
+                .strip_prefix("{{")
+                .and_then(|inner| inner.strip_suffix("}}"))
+                .filter(|inner| !inner.contains("{{") && !inner.contains("}}"));
+            match single {
+                Some(path) => lookup(path, context)
+                    .cloned()
+                    .unwrap_or(serde_json::Value::Null),
+                None => render(text, context).into(),
+            }
+        }
+        serde_json::Value::Array(array) => array
+            .iter()
+            .map(|value| render_json(value, context))
+            .collect(),
+        serde_json::Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| (render(key, context), render_json(value, context)))
+            .collect(),
+        other => other.clone(),
+    }
+}
+
+fn lookup<'a>(path: &str, context: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+    path.trim()
+        .split('.')
+        .try_fold(context, |value, key| match value {
+            serde_json::Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+            _ => value.get(key),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escape;
+
+    #[test]
+    fn render_replaces_placeholders() {
+        let context = serde_json::json!({ "labels": { "severity": "critical" }, "count": 2 });
+        assert_eq!(
+            render(
+                "{{count}} alerts ({{labels.severity}}{{missing}})",
+                &context
+            ),
+            "2 alerts (critical)"
+        );
+    }
+
+    #[test]
+    fn render_with_encodes_the_values_only() {
+        let context = serde_json::json!({ "team": "../admin?x=1#", "status": "firing" });
+        assert_eq!(
+            render_with(
+                "https://example.com/{{team}}/alerts?status={{status}}",
+                &context,
+                escape::url_component
+            ),
+            "https://example.com/..%2Fadmin%3Fx%3D1%23/alerts?status=firing"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use jiff::tz::TimeZone;
+use jiff::{Timestamp, civil};
+
+/// Look up the IANA timezone (e.g. "Europe/Berlin"), in which the times are shown
+pub fn timezone(name: &Option<String>) -> Result<TimeZone, String> {
+    match name {
+        Some(name) => TimeZone::get(name).map_err(|e| format!("Unknown timezone {}: {}", name, e)),
+        None => Ok(TimeZone::UTC),
+    }
+}
+
+/// Parse a RFC 3339 timestamp, as used by Grafana (the zero time of unset values is ignored)
+pub fn parse_rfc3339(text: &str) -> Option<Timestamp> {
+    text.parse::<Timestamp>()
+        .ok()
+        .filter(|timestamp| timestamp.as_second() > 0)
+}
+
+/// Parse a timestamp without offset, which is in UTC (e.g. "2024-01-31 14:02:03.123" of UptimeKuma)
+pub fn parse_utc(text: &str) -> Option<Timestamp> {
+    let datetime = text.replacen(' ', "T", 1).parse::<civil::DateTime>().ok()?;
+    datetime
+        .to_zoned(TimeZone::UTC)
+        .ok()
+        .map(|zoned| zoned.timestamp())
+}
+
+/// Show the time of the day (with the date, if it is not the day of `now`), e.g. "14:02" or "2024-01-31 14:02"
+pub fn format_time(timestamp: Timestamp, now: Timestamp, timezone: &TimeZone) -> String {
+    let zoned = timestamp.to_zoned(timezone.clone());
+    let today = now.to_zoned(timezone.clone()).date();
+    if zoned.date() == today {
+        zoned.strftime("%H:%M").to_string()
+    } else {
+        zoned.strftime("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// Show the duration with its two largest units, e.g. "45s", "23m", "1h 5m" or "2d 3h"
+pub fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let Some(first) = units.iter().position(|(value, _)| *value > 0) else {
+        return "0s".to_string();
+    };
+    units[first..]
+        .iter()
+        .take(2)
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The seconds elapsed since the timestamp until `now` (zero, if it is in the future, e.g. due to clock skew)
+pub fn seconds_since(timestamp: Timestamp, now: Timestamp) -> i64 {
+    (now.as_second() - timestamp.as_second()).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(text: &str) -> Timestamp {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn durations_show_the_two_largest_units() {
+        for (seconds, expected) in [
+            (-5, "0s"),
+            (0, "0s"),
+            (45, "45s"),
+            (60, "1m"),
+            (23 * 60 + 7, "23m 7s"),
+            (3600, "1h"),
+            (3600 + 5 * 60 + 9, "1h 5m"),
+            (3600 + 9, "1h"), // the seconds are not the second largest unit
+            (2 * 86400 + 3 * 3600 + 59, "2d 3h"),
+            (400 * 86400, "400d"),
+        ] {
+            assert_eq!(format_duration(seconds), expected, "{}", seconds);
+        }
+    }
+
+    #[test]
+    fn timestamps_are_parsed() {
+        assert_eq!(
+            parse_rfc3339("2024-01-31T14:02:03.123+01:00"),
+            Some(timestamp("2024-01-31T13:02:03.123Z"))
+        );
+        assert_eq!(
+            parse_utc("2024-01-31 14:02:03.123"),
+            Some(timestamp("2024-01-31T14:02:03.123Z"))
+        );
+        assert_eq!(
+            parse_utc("2024-01-31T14:02:03"),
+            Some(timestamp("2024-01-31T14:02:03Z"))
+        );
+        for text in ["", "yesterday", "2024-13-01T00:00:00Z", "2024-01-31 14:02"] {
+            assert_eq!(parse_rfc3339(text), None, "{}", text);
+        }
+        for text in ["", "yesterday", "2024-02-30 10:00:00", "14:02:03"] {
+            assert_eq!(parse_utc(text), None, "{}", text);
+        }
+        // Grafana sends the zero time for unset values
+        assert_eq!(parse_rfc3339("0001-01-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn times_show_the_date_on_other_days() {
+        let berlin = timezone(&Some("Europe/Berlin".to_string())).unwrap();
+        // 22:30 UTC is already the next day in Berlin (CEST, +02:00)
+        let now = timestamp("2024-06-01T22:30:00Z");
+        assert_eq!(
+            format_time(timestamp("2024-06-01T22:05:00Z"), now, &berlin),
+            "00:05"
+        );
+        assert_eq!(
+            format_time(timestamp("2024-06-01T21:55:00Z"), now, &berlin),
+            "2024-06-01 23:55"
+        );
+        assert_eq!(
+            format_time(timestamp("2024-06-01T21:55:00Z"), now, &TimeZone::UTC),
+            "21:55"
+        );
+        assert!(timezone(&Some("Mars/Olympus".to_string())).is_err());
+        assert_eq!(timezone(&None).unwrap(), TimeZone::UTC);
+    }
+
+    #[test]
+    fn future_timestamps_have_not_elapsed() {
+        let now = timestamp("2024-06-01T12:00:00Z");
+        assert_eq!(seconds_since(timestamp("2024-06-01T11:58:30Z"), now), 90);
+        assert_eq!(seconds_since(timestamp("2024-06-01T12:05:00Z"), now), 0);
+    }
+}
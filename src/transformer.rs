@@ -1,7 +1,14 @@
-use actix_web::{web, HttpRequest};
-use log::{debug, warn};
+use crate::http::HttpClientConfig;
+use crate::locale::Language;
+use crate::message::{Entry, Link, Message, Origin, Span, Status};
+use crate::sink::{self, SinkConfigTypes, hookshot::HookshotSink};
+use crate::time;
+use actix_web::{HttpRequest, web};
+use jiff::Timestamp;
+use log::debug;
 use serde::{Deserialize, Serialize};
-use serde_json;
+use serde_json::{self, json};
+use std::collections::{BTreeMap, LinkedList};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransformerConfigTypes {
@@ -16,50 +23,319 @@ impl TransformerConfigTypes {
     pub async fn handle(&self, request: &HttpRequest, body: &web::Bytes) -> Result<(), String> {
         match self {
             TransformerConfigTypes::GrafanaToHookshot(inner_transformer) => {
-                inner_transformer.handle(&request, &body).await
+                inner_transformer.handle(request, body).await
             }
             TransformerConfigTypes::UptimeKumaToHookshot(inner_transformer) => {
-                inner_transformer.handle(&request, &body).await
+                inner_transformer.handle(request, body).await
             }
             TransformerConfigTypes::GitlabToHookshot(inner_transformer) => {
-                inner_transformer.handle(&request, &body).await
+                inner_transformer.handle(request, body).await
             }
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct HookshotMessage {
-    text: String,             // automatically converted from Markdown to HTML
-    html: Option<String>,     // if not provided, the text will be (converted and) used
-    username: Option<String>, // will be prepended to the message
-}
-impl HookshotMessage {
-    async fn submit(&self, uri: &str) -> Result<(), String> {
-        debug!("Submitting message to Hookshot (via {}): {:#?}", uri, self);
-        let client = reqwest::Client::new();
-        client
-            .post(uri)
-            .body(serde_json::to_string(self).map_err(|e| e.to_string())?)
-            .header("Content-Type", "application/json")
-            .timeout(std::time::Duration::from_secs(10))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+impl TransformerConfigTypes {
+    /// Build the HTTP clients of the transformer and its sinks, so that configuration errors show up at startup
+    pub fn validate(&self) -> Result<(), String> {
+        let (http, sinks) = match self {
+            TransformerConfigTypes::GrafanaToHookshot(inner) => (&inner.http, &inner.sinks),
+            TransformerConfigTypes::UptimeKumaToHookshot(inner) => (&inner.http, &inner.sinks),
+            TransformerConfigTypes::GitlabToHookshot(inner) => (&inner.http, &inner.sinks),
+        };
+        http.client()?;
+        for sink in sinks.iter().flatten() {
+            sink.validate()?;
+        }
         Ok(())
     }
 }
 
+/// Submit the message to the Hookshot `uri` (if any) and all other configured sinks
+async fn submit(
+    uri: &Option<String>,
+    http: &HttpClientConfig,
+    sinks: &Option<LinkedList<SinkConfigTypes>>,
+    origin: &Origin,
+    mut message: Message,
+) -> Result<(), String> {
+    message.origin = Some(origin.clone());
+    message.sanitize();
+    let hookshot = uri.as_ref().map(|uri| {
+        SinkConfigTypes::Hookshot(HookshotSink {
+            uri: uri.clone(),
+            limit: None,
+            http: http.clone(), // shares the client
+        })
+    });
+    let all_sinks: Vec<&SinkConfigTypes> = hookshot.iter().chain(sinks.iter().flatten()).collect();
+    if all_sinks.is_empty() {
+        return Err("Neither an uri nor any sinks are configured".to_string());
+    }
+    sink::submit_all(all_sinks, &message).await
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrafanaToHookshotTransformer {
-    uri: String,
+    uri: Option<String>,        // shortcut for a single Hookshot sink
+    language: Option<Language>, // (default: En) of the generated wording, e.g. "De"
     just_show_message: Option<bool>,
+    values: Option<GrafanaValues>, // (default: all values, with 2 decimal places)
+    headline_annotation: Option<String>, // (default: "summary") shown next to the alert name
+    show_labels: Option<Vec<String>>, // (default: none) shown as "key=value" line, "*" for all
+    hide_labels: Option<Vec<String>>, // (default: "__alert_rule_uid__", "grafana_folder") not passed on
+    group_by: Option<String>, // (default: none) label to group the alerts by, e.g. "namespace"
+    timezone: Option<String>, // (default: "UTC") IANA timezone of the shown times, e.g. "Europe/Berlin"
+    #[serde(default)]
+    http: HttpClientConfig,
+    sinks: Option<LinkedList<SinkConfigTypes>>,
+}
+
+/// How to show the current values of the queries and expressions of an alert
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GrafanaValues {
+    precision: Option<usize>, // (default: 2) decimal places, trailing zeros are removed
+    refs: Option<BTreeMap<String, GrafanaValueRef>>, // (default: all) shown values by their ref id, e.g. "B"
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GrafanaValueRef {
+    name: Option<String>, // (default: the ref id) e.g. "CPU"
+    unit: Option<String>, // appended to the value, e.g. "%" or " MB"
+}
+
+impl GrafanaValues {
+    /// Render the values as "name value" spans, e.g. "CPU 97.3%"
+    fn spans(&self, values: &[(String, f64)]) -> Vec<Span> {
+        let mut spans = Vec::new();
+        for (ref_id, value) in values {
+            let display = match &self.refs {
+                Some(refs) => match refs.get(ref_id) {
+                    Some(display) => display.clone(),
+                    None => continue, // not selected
+                },
+                None => GrafanaValueRef::default(),
+            };
+            let mut number = format!("{:.*}", self.precision.unwrap_or(2), value);
+            if number.contains('.') {
+                number = number
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string();
+            }
+            spans.push(Span::plain(if spans.is_empty() { " · " } else { ", " }));
+            spans.push(Span::plain(&format!(
+                "{} ",
+                display.name.as_deref().unwrap_or(ref_id)
+            )));
+            spans.push(Span::bold(
+                &(number + display.unit.as_deref().unwrap_or("")),
+            ));
+        }
+        spans
+    }
+}
+
+/// Render an outgoing webhook of Grafana OnCall, about a state change of an alert group
+fn grafana_oncall_message(
+    body: &serde_json::Map<String, serde_json::Value>,
+    language: Language,
+) -> Result<Message, String> {
+    let alert_group = body
+        .get("alert_group")
+        .ok_or("The body does not contain an alert_group".to_string())?;
+    let alert_group = alert_group
+        .as_object()
+        .ok_or("The alert_group is not a JSON object".to_string())?;
+    let fallback_title = language.text("oncall.title", &json!({}));
+    let title = alert_group
+        .get("title")
+        .and_then(|v| v.as_str())
+        .unwrap_or(&fallback_title);
+    let state = alert_group
+        .get("state")
+        .ok_or("The alert_group does not contain a state".to_string())?;
+    let state = state
+        .as_str()
+        .ok_or("The state is not a string".to_string())?;
+    let status = match state {
+        "firing" | "new" => Status::Firing,
+        "acknowledged" => Status::Pending,
+        "resolved" => Status::Resolved,
+        "silenced" => Status::Info,
+        _ => Status::Unknown,
+    };
+    let url = alert_group
+        .get("permalinks")
+        .and_then(|v| v.get("web"))
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty());
+    let event_type = body
+        .get("event")
+        .and_then(|v| v.get("type"))
+        .and_then(|v| v.as_str())
+        .unwrap_or(state);
+    let user = body
+        .get("user")
+        .and_then(|v| v.get("username"))
+        .and_then(|v| v.as_str());
+
+    let mut headline = vec![Span::bold(title)];
+    let state_text = match state {
+        "firing" | "new" | "acknowledged" | "resolved" | "silenced" => {
+            language.text(&format!("oncall.state.{}", state), &json!({}))
+        }
+        other => other.to_string(),
+    };
+    headline.push(Span::plain(
+        &language.text("oncall.state", &json!({ "state": state_text })),
+    ));
+    let mut entry = Entry::new(status, headline);
+    // Describe the event, e.g. who acknowledged the alert group
+    let event = match event_type {
+        "escalation" | "acknowledge" | "unacknowledge" | "resolve" | "unresolve" | "silence"
+        | "unsilence" => language.text(&format!("oncall.event.{}", event_type), &json!({})),
+        other => language.text("oncall.event.other", &json!({ "event": other })),
+    };
+    let mut details = vec![Span::plain(&event)];
+    if let Some(user) = user {
+        details.push(Span::plain(&language.text("oncall.by", &json!({}))));
+        details.push(Span::bold(user));
+    }
+    if let Some(count) = alert_group.get("alerts_count").and_then(|v| v.as_u64()) {
+        details.push(Span::plain(
+            &language.text("oncall.alerts", &json!({ "count": count })),
+        ));
+    }
+    entry.details.push(details);
+    entry.fingerprint = alert_group
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string());
+    if let Some(integration) = body
+        .get("integration")
+        .and_then(|v| v.get("name"))
+        .and_then(|v| v.as_str())
+    {
+        entry
+            .labels
+            .insert("integration".to_string(), integration.to_string());
+    }
+    if let Some(url) = url {
+        entry.links.push(Link {
+            label: language.text("link.alert_group", &json!({})),
+            url: url.to_string(),
+        });
+    }
+
+    Ok(Message {
+        status,
+        title: Vec::new(),
+        entries: vec![entry],
+        body: None,
+        url: url.map(|url| url.to_string()),
+        language,
+        origin: None,
+    })
+}
+
+/// Group the alerts by the value of the label, the groups with the most severe alerts first (but those without the label last)
+fn group_entries(entries: Vec<Entry>, label: &str, language: Language) -> Vec<Entry> {
+    let mut groups: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+    for entry in entries {
+        let value = entry.labels.get(label).cloned().unwrap_or_default();
+        groups.entry(value).or_default().push(entry);
+    }
+    let mut groups: Vec<(String, Vec<Entry>)> = groups.into_iter().collect();
+    groups.sort_by_key(|(value, entries)| {
+        let rank = entries
+            .iter()
+            .map(|entry| severity_rank(entry.labels.get("severity")))
+            .min();
+        (value.is_empty(), rank) // alerts without the label last
+    });
+
+    let mut grouped = Vec::new();
+    for (value, mut entries) in groups {
+        let mut counts = Vec::new();
+        for (status, key) in [
+            (Status::Firing, "grafana.count.firing"),
+            (Status::Pending, "grafana.count.pending"),
+            (Status::Resolved, "grafana.count.resolved"),
+        ] {
+            let count = entries
+                .iter()
+                .filter(|entry| entry.status == status)
+                .count();
+            if count > 0 {
+                counts.push(language.text(key, &json!({ "count": count })));
+            }
+        }
+        let heading = match value.as_str() {
+            "" => language.text("grafana.group.without", &json!({ "label": label })),
+            value => format!("{}={}", label, value),
+        };
+        let heading = match counts.is_empty() {
+            true => heading,
+            false => format!("{} ({})", heading, counts.join(", ")),
+        };
+        for entry in &mut entries {
+            entry.group = Some(heading.clone());
+        }
+        grouped.extend(entries);
+    }
+    grouped
+}
+
+/// Order of the usual values of the "severity" label, the most severe first (unknown ones last)
+fn severity_rank(severity: Option<&String>) -> usize {
+    match severity.map(|severity| severity.to_lowercase()).as_deref() {
+        Some("critical" | "disaster" | "emergency") => 0,
+        Some("high" | "error" | "major") => 1,
+        Some("warning" | "average" | "medium" | "minor") => 2,
+        Some("low") => 3,
+        Some("info" | "information" | "none") => 4,
+        _ => 5,
+    }
+}
+
+/// Extract the values of an alert, either from "values" or (for older versions) the "valueString"
+fn grafana_values(alert: &serde_json::Map<String, serde_json::Value>) -> Vec<(String, f64)> {
+    if let Some(values) = alert.get("values").and_then(|v| v.as_object())
+        && !values.is_empty()
+    {
+        return values
+            .iter()
+            .filter_map(|(ref_id, value)| Some((ref_id.clone(), value.as_f64()?)))
+            .collect();
+    }
+    // e.g. "[ var='B' labels={instance=db1} value=97.31 ], [ var='C' labels={instance=db1} value=1 ]"
+    let value_string = alert
+        .get("valueString")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let mut values = Vec::new();
+    for part in value_string.split("var='").skip(1) {
+        let Some((ref_id, rest)) = part.split_once('\'') else {
+            continue;
+        };
+        let value = rest
+            .split_once("value=")
+            .and_then(|(_, value)| value.split([' ', ']', ',']).next())
+            .and_then(|value| value.parse::<f64>().ok());
+        if let Some(value) = value.filter(|value| value.is_finite()) {
+            values.push((ref_id.to_string(), value));
+        }
+    }
+    values
 }
+
 impl GrafanaToHookshotTransformer {
     async fn handle(&self, request: &HttpRequest, body: &web::Bytes) -> Result<(), String> {
         if request.method() != "POST" && request.method() != "PUT" {
             return Err("Only POST and PUT requests are supported".to_string());
         }
+        let origin = Origin::new(request, body);
 
         let body = String::from_utf8(body.to_vec())
             .map_err(|e| "Failed to parse the body as UTF-8: ".to_string() + &e.to_string())?;
@@ -71,25 +347,49 @@ impl GrafanaToHookshotTransformer {
             .as_object()
             .ok_or("The body is not a JSON object".to_string())?;
 
-        if self.just_show_message.unwrap_or(false) {
+        let message = self.message(body)?;
+        submit(&self.uri, &self.http, &self.sinks, &origin, message).await
+    }
+
+    /// Render the alerts (or the OnCall event) as message
+    fn message(
+        &self,
+        body: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Message, String> {
+        if body.contains_key("alert_group") {
+            // Grafana OnCall has no message, which could just be shown
+            grafana_oncall_message(body, self.language.unwrap_or_default())
+        } else if self.just_show_message.unwrap_or(false) {
             let message = body
                 .get("message")
                 .ok_or("The body does not contain a message".to_string())?;
             let message = message
                 .as_str()
                 .ok_or("The message is not a string".to_string())?;
-            let message = HookshotMessage {
-                text: message.to_string(), // Grafana already sends Markdown
-                html: None,
-                username: None,
+            // The legacy alerting uses the "state" instead
+            let status = match body
+                .get("status")
+                .or(body.get("state"))
+                .and_then(|v| v.as_str())
+            {
+                Some("firing" | "alerting") => Status::Firing,
+                Some("resolved" | "ok") => Status::Resolved,
+                _ => Status::Unknown,
             };
-            message.submit(&self.uri).await
+            let mut message = Message::preformatted(status, message); // Grafana already sends Markdown
+            message.language = self.language.unwrap_or_default();
+            Ok(message)
+        } else if !body.contains_key("alerts") && body.contains_key("ruleName") {
+            self.legacy_message(body)
         } else {
+            let timezone = time::timezone(&self.timezone)?;
+            let now = Timestamp::now();
+            let language = self.language.unwrap_or_default();
             // Count how many alerts are raised (and how many are resolved)
             let mut alerts_firing = 0;
             let mut alerts_alerting = 0;
             let mut alerts_resolved = 0;
-            let mut alert_list = std::collections::LinkedList::new();
+            let mut entries = Vec::new();
             let alerts = body
                 .get("alerts")
                 .ok_or("The body does not contain alerts".to_string())?;
@@ -129,7 +429,7 @@ impl GrafanaToHookshotTransformer {
                     .as_str()
                     .ok_or("An alert's alertname in its labels is not a string".to_string())?;
 
-                let instance = labels.get("instance").map(|v| v.as_str()).flatten();
+                let instance = labels.get("instance").and_then(|v| v.as_str());
 
                 let annotations = alert
                     .get("annotations")
@@ -138,181 +438,326 @@ impl GrafanaToHookshotTransformer {
                     .as_object()
                     .ok_or("An alert's annotations are not an object".to_string())?;
 
-                let summary = annotations.get("summary").map(|v| v.as_str()).flatten();
-                let description = annotations.get("description").map(|v| v.as_str()).flatten();
+                let headline_annotation = self.headline_annotation.as_deref().unwrap_or("summary");
+                let summary = annotations
+                    .get(headline_annotation)
+                    .and_then(|v| v.as_str());
+                let description = annotations
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .filter(|_| headline_annotation != "description");
+                let runbook_url = annotations.get("runbook_url").and_then(|v| {
+                    v.as_str()
+                        .and_then(|v| if !v.is_empty() { Some(v) } else { None })
+                });
 
-                // TODO values?
+                let values = grafana_values(alert);
 
-                let silence_url = alert
-                    .get("silenceURL")
-                    .map(|v| {
-                        v.as_str()
-                            .map(|v| if v.len() > 0 { Some(v) } else { None })
-                            .flatten()
-                    })
-                    .flatten();
-                let panel_url = alert
-                    .get("panelURL")
-                    .map(|v| {
-                        v.as_str()
-                            .map(|v| if v.len() > 0 { Some(v) } else { None })
-                            .flatten()
-                    })
-                    .flatten();
-                let dashboard_url = alert
-                    .get("dashboardURL")
-                    .map(|v| {
+                let silence_url = alert.get("silenceURL").and_then(|v| {
+                    v.as_str()
+                        .and_then(|v| if !v.is_empty() { Some(v) } else { None })
+                });
+                let panel_url = alert.get("panelURL").and_then(|v| {
+                    v.as_str()
+                        .and_then(|v| if !v.is_empty() { Some(v) } else { None })
+                });
+                let dashboard_url = alert.get("dashboardURL").and_then(|v| {
+                    v.as_str()
+                        .and_then(|v| if !v.is_empty() { Some(v) } else { None })
+                });
+                let image_url = alert
+                    .get("imageURL")
+                    .or(alert.get("imageUrl")) // legacy alerts
+                    .and_then(|v| {
                         v.as_str()
-                            .map(|v| if v.len() > 0 { Some(v) } else { None })
-                            .flatten()
-                    })
-                    .flatten();
-                let actions = {
-                    let mut actions = std::collections::LinkedList::new();
-                    if dashboard_url.is_some() {
-                        actions.push_back(format!(
-                            "<a href=\"{}\">dashboard</a>",
-                            dashboard_url.unwrap()
-                        ));
-                    }
-                    if panel_url.is_some() {
-                        actions.push_back(format!("<a href=\"{}\">panel</a>", panel_url.unwrap()));
-                    }
-                    if silence_url.is_some() {
-                        actions
-                            .push_back(format!("<a href=\"{}\">silence</a>", silence_url.unwrap()));
-                    }
-                    if actions.len() > 0 {
-                        let mut actions_str = "→ ".to_string() + &actions.front().unwrap();
-                        for line in actions.iter().skip(1) {
-                            actions_str += ", ";
-                            actions_str += line;
-                        }
-                        Some(actions_str)
-                    } else {
-                        None
-                    }
-                };
+                            .and_then(|v| if !v.is_empty() { Some(v) } else { None })
+                    });
 
-                // Create the alert string
-                let mut as_multiline_str = std::collections::LinkedList::new();
-                as_multiline_str.push_back(format!(
-                    "{} <b>{}</b>{}{}",
+                // Create the alert entry
+                let mut headline = vec![Span::bold(alertname)];
+                if let Some(instance) = instance {
+                    headline.push(Span::plain(&language.text("grafana.instance", &json!({}))));
+                    headline.push(Span::code(instance));
+                }
+                if let Some(summary) = summary {
+                    headline.push(Span::plain(": "));
+                    headline.push(Span::plain(summary));
+                }
+                headline.extend(self.values.clone().unwrap_or_default().spans(&values));
+                let mut entry = Entry::new(
                     match status {
-                        "firing" => {
-                            "🔴"
-                        }
-                        "alerting" => {
-                            "🟡"
-                        }
-                        "resolved" => {
-                            "🟢"
-                        }
-                        _ => {
-                            "⚪"
-                        }
-                    },
-                    alertname,
-                    if instance.is_some() {
-                        format!(" at <code>{}</code>", instance.unwrap())
-                    } else {
-                        "".to_string()
+                        "firing" => Status::Firing,
+                        "alerting" => Status::Pending,
+                        "resolved" => Status::Resolved,
+                        _ => Status::Unknown,
                     },
-                    if summary.is_some() {
-                        format!(": {}", summary.unwrap())
-                    } else {
-                        "".to_string()
-                    }
-                ));
+                    headline,
+                );
+                // Use the labels as fallback fingerprint
+                entry.fingerprint = Some(match alert.get("fingerprint").and_then(|v| v.as_str()) {
+                    Some(fingerprint) => fingerprint.to_string(),
+                    None => serde_json::to_string(labels).map_err(|e| e.to_string())?,
+                });
                 // Add description
-                if description.is_some() {
-                    as_multiline_str.push_back(description.unwrap().to_string());
+                if let Some(description) = description {
+                    entry.details.push(vec![Span::plain(description)]);
+                }
+                // Add since when the alert is firing (or how long it was)
+                let starts_at = alert
+                    .get("startsAt")
+                    .and_then(|v| v.as_str())
+                    .and_then(time::parse_rfc3339);
+                let ends_at = alert
+                    .get("endsAt")
+                    .and_then(|v| v.as_str())
+                    .and_then(time::parse_rfc3339);
+                if let Some(starts_at) = starts_at {
+                    let since = time::format_time(starts_at, now, &timezone);
+                    let duration = time::format_duration(time::seconds_since(starts_at, now));
+                    let key = match (status, ends_at) {
+                        ("resolved", Some(_)) => "grafana.since.resolved",
+                        ("resolved", None) => "grafana.since.resolved.started",
+                        ("alerting", _) => "grafana.since.pending",
+                        _ => "grafana.since.firing",
+                    };
+                    let (until, duration) = match ends_at {
+                        Some(ends_at) if status == "resolved" => (
+                            time::format_time(ends_at, now, &timezone),
+                            time::format_duration(ends_at.as_second() - starts_at.as_second()),
+                        ),
+                        _ => (String::new(), duration),
+                    };
+                    let line = language.text(
+                        key,
+                        &json!({ "since": since, "until": until, "duration": duration }),
+                    );
+                    entry.details.push(vec![Span::plain(&line)]);
                 }
+                self.add_labels(&mut entry, labels);
                 // Add actions
-                if actions.is_some() {
-                    as_multiline_str.push_back(actions.unwrap());
+                for (key, url) in [
+                    ("link.runbook", runbook_url),
+                    ("link.dashboard", dashboard_url),
+                    ("link.panel", panel_url),
+                    ("link.silence", silence_url),
+                    ("link.image", image_url),
+                ] {
+                    if let Some(url) = url {
+                        entry.links.push(Link {
+                            label: language.text(key, &json!({})),
+                            url: url.to_string(),
+                        });
+                    }
                 }
-                alert_list.push_back(as_multiline_str);
+                entry.image = image_url.map(|url| url.to_string());
+                entries.push(entry);
+            }
+            if let Some(label) = &self.group_by {
+                entries = group_entries(entries, label, language);
             }
+            // Link the dashboard of the first alert (or Grafana itself), in case alerts are omitted
+            let url = alerts
+                .iter()
+                .filter_map(|alert| alert.get("dashboardURL").and_then(|v| v.as_str()))
+                .chain(body.get("externalURL").and_then(|v| v.as_str()))
+                .find(|url| !url.is_empty())
+                .map(|url| url.to_string());
             // Create the message (title)
+            // The other alerts are mentioned in parentheses, e.g. "(1 pending and 2 resolved)"
+            let others = |counts: &[(usize, &str)]| {
+                let list: Vec<String> = counts
+                    .iter()
+                    .filter(|(count, _)| *count > 0)
+                    .map(|(count, key)| language.text(key, &json!({ "count": count })))
+                    .collect();
+                match list.is_empty() {
+                true => String::new(),
+                false => language.text(
+                    "grafana.others",
+                    &json!({ "list": list.join(&language.text("grafana.others.and", &json!({}))) }),
+                ),
+            }
+            };
             let title = if alerts_firing > 0 {
-                format!(
-                    "🚨 {} alert{} firing{}",
-                    alerts_firing,
-                    if alerts_firing == 1 { " is" } else { "s are" },
-                    if alerts_alerting > 0 || alerts_resolved > 0 {
-                        format!(
-                            " ({}{}{})",
-                            if alerts_alerting > 0 {
-                                format!("{} pending", alerts_alerting)
-                            } else {
-                                "".to_string()
-                            },
-                            if alerts_alerting > 0 && alerts_resolved > 0 {
-                                " and ".to_string()
-                            } else {
-                                "".to_string()
-                            },
-                            if alerts_resolved > 0 {
-                                format!("{} resolved", alerts_resolved)
-                            } else {
-                                "".to_string()
-                            }
-                        )
-                    } else {
-                        "".to_string()
-                    }
+                let others = others(&[
+                    (alerts_alerting, "grafana.count.pending"),
+                    (alerts_resolved, "grafana.count.resolved"),
+                ]);
+                language.text(
+                    "grafana.firing",
+                    &json!({ "count": alerts_firing, "others": others }),
                 )
             } else if alerts_alerting > 0 {
-                format!(
-                    "⚠️ {} alert{} pending{}...",
-                    alerts_alerting,
-                    if alerts_alerting == 1 { " is" } else { "s are" },
-                    if alerts_resolved > 0 {
-                        format!(" ({} resolved)", alerts_resolved)
-                    } else {
-                        "".to_string()
-                    }
+                let others = others(&[(alerts_resolved, "grafana.count.resolved")]);
+                language.text(
+                    "grafana.pending",
+                    &json!({ "count": alerts_alerting, "others": others }),
                 )
             } else {
-                "✅ All alerts are resolved!".to_string()
+                language.text("grafana.resolved", &json!({}))
             };
-            let mut message_html = format!("<h3>{}</h3>", title);
-            // Append the alerts
-            for alert in alert_list {
-                if alert.len() == 0 {
-                    warn!("An alert is empty?! This is a bug!");
-                    continue;
-                }
-                message_html += "<p>";
-                message_html += alert.front().unwrap();
-                for line in alert.iter().skip(1) {
-                    message_html += "<br>";
-                    message_html += line;
-                }
-                message_html += "</p>";
-            }
             // Final message
-            let message = HookshotMessage {
-                text: title,
-                html: Some(message_html),
-                username: None,
+            let message = Message {
+                status: if alerts_firing > 0 {
+                    Status::Firing
+                } else if alerts_alerting > 0 {
+                    Status::Pending
+                } else {
+                    Status::Resolved
+                },
+                title: vec![Span::plain(&title)],
+                entries,
+                body: None,
+                url,
+                language: self.language.unwrap_or_default(),
+                origin: None,
             };
-            message.submit(&self.uri).await
+            Ok(message)
+        }
+    }
+
+    /// Copy the labels into the entry (without the hidden ones) and show the selected ones as a line
+    fn add_labels(&self, entry: &mut Entry, labels: &serde_json::Map<String, serde_json::Value>) {
+        let hidden = match &self.hide_labels {
+            Some(hidden) => hidden.clone(),
+            None => vec![
+                "__alert_rule_uid__".to_string(),
+                "grafana_folder".to_string(),
+            ],
+        };
+        for (key, value) in labels {
+            if let Some(value) = value.as_str()
+                && !hidden.contains(key)
+            {
+                entry.labels.insert(key.clone(), value.to_string());
+            }
+        }
+        let Some(shown) = &self.show_labels else {
+            return;
+        };
+        let shown: Vec<(&String, &String)> = if shown.iter().any(|key| key == "*") {
+            // The alertname and instance are already part of the headline
+            entry
+                .labels
+                .iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "alertname" | "instance"))
+                .collect()
+        } else {
+            shown
+                .iter()
+                .filter_map(|key| entry.labels.get_key_value(key))
+                .collect()
+        };
+        let mut line = Vec::new();
+        for (key, value) in shown {
+            if !line.is_empty() {
+                line.push(Span::plain(", "));
+            }
+            line.push(Span::plain(&format!("{}=", key)));
+            line.push(Span::code(value));
+        }
+        if !line.is_empty() {
+            entry.details.push(line);
         }
     }
+
+    /// Render an alert of the legacy alerting (before Grafana 9), which only contains a single rule
+    fn legacy_message(
+        &self,
+        body: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Message, String> {
+        let rule_name = body
+            .get("ruleName")
+            .ok_or("The body does not contain a ruleName".to_string())?;
+        let rule_name = rule_name
+            .as_str()
+            .ok_or("The ruleName is not a string".to_string())?;
+        let state = body
+            .get("state")
+            .ok_or("The body does not contain a state".to_string())?;
+        let state = state
+            .as_str()
+            .ok_or("The state is not a string".to_string())?;
+        let status = match state {
+            "alerting" => Status::Firing,
+            "pending" => Status::Pending,
+            "ok" => Status::Resolved,
+            "paused" => Status::Info,
+            _ => Status::Unknown, // e.g. no_data
+        };
+        let non_empty = |key: &str| {
+            body.get(key)
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+        };
+
+        let mut headline = vec![Span::bold(rule_name)];
+        if let Some(message) = non_empty("message") {
+            headline.push(Span::plain(": "));
+            headline.push(Span::plain(message));
+        }
+        // The matches are the values of the series, which exceeded the threshold
+        let values: Vec<(String, f64)> = body
+            .get("evalMatches")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|eval_match| {
+                let metric = eval_match.get("metric")?.as_str()?;
+                Some((metric.to_string(), eval_match.get("value")?.as_f64()?))
+            })
+            .collect();
+        headline.extend(self.values.clone().unwrap_or_default().spans(&values));
+
+        let mut entry = Entry::new(status, headline);
+        entry.fingerprint = Some(match body.get("ruleId").and_then(|v| v.as_u64()) {
+            Some(rule_id) => rule_id.to_string(),
+            None => rule_name.to_string(),
+        });
+        if let Some(tags) = body.get("tags").and_then(|v| v.as_object()) {
+            self.add_labels(&mut entry, tags);
+        }
+        for (key, url) in [
+            ("link.rule", non_empty("ruleUrl")),
+            ("link.image", non_empty("imageUrl")),
+        ] {
+            if let Some(url) = url {
+                entry.links.push(Link {
+                    label: self.language.unwrap_or_default().text(key, &json!({})),
+                    url: url.to_string(),
+                });
+            }
+        }
+        entry.image = non_empty("imageUrl").map(|url| url.to_string());
+
+        Ok(Message {
+            status,
+            title: Vec::new(),
+            entries: vec![entry],
+            body: None,
+            url: non_empty("ruleUrl").map(|url| url.to_string()),
+            language: self.language.unwrap_or_default(),
+            origin: None,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UptimeKumaToHookshotTransformer {
-    uri: String,
+    uri: Option<String>,        // shortcut for a single Hookshot sink
+    language: Option<Language>, // (default: En) of the generated wording, e.g. "De"
     just_show_message: Option<bool>,
+    timezone: Option<String>, // (default: "UTC") IANA timezone of the shown times, e.g. "Europe/Berlin"
+    #[serde(default)]
+    http: HttpClientConfig,
+    sinks: Option<LinkedList<SinkConfigTypes>>,
 }
 impl UptimeKumaToHookshotTransformer {
     async fn handle(&self, request: &HttpRequest, body: &web::Bytes) -> Result<(), String> {
         if request.method() != "POST" {
             return Err("Only POST requests are supported".to_string());
         }
+        let origin = Origin::new(request, body);
 
         let body = String::from_utf8(body.to_vec())
             .map_err(|e| "Failed to parse the body as UTF-8: ".to_string() + &e.to_string())?;
@@ -331,12 +776,9 @@ impl UptimeKumaToHookshotTransformer {
             let message = message
                 .as_str()
                 .ok_or("The msg is not a string".to_string())?;
-            let message = HookshotMessage {
-                text: message.to_string(), // UptimeKuma not uses Markdown, but fany emojis
-                html: None,
-                username: None,
-            };
-            message.submit(&self.uri).await
+            let mut message = Message::preformatted(uptime_kuma_status(body), message); // UptimeKuma not uses Markdown, but fany emojis
+            message.language = self.language.unwrap_or_default();
+            submit(&self.uri, &self.http, &self.sinks, &origin, message).await
         } else {
             let heartbeat = body
                 .get("heartbeat")
@@ -357,52 +799,86 @@ impl UptimeKumaToHookshotTransformer {
             let message = message
                 .as_str()
                 .ok_or("The msg is not a string".to_string())?;
-            let monitor_msg = body.get("msg");
-            let mut is_up = None;
-            if let Some(monitor_msg) = monitor_msg {
-                let monitor_msg = monitor_msg.as_str().unwrap_or(""); // if this is not a string, treat it as empty
-                if monitor_msg.contains("[✅ ") {
-                    is_up = Some(true);
-                } else if monitor_msg.contains("[🔴 ") {
-                    is_up = Some(false);
-                } else if monitor_msg.contains("Up]") {
-                    // well, try that again with a little bit more fuzzy matching
-                    is_up = Some(true);
-                } else if monitor_msg.contains("Down]") {
-                    // well, try that again with a little bit more fuzzy matching
-                    is_up = Some(false);
-                }
-            }
-
-            let message_html = format!(
-                "<p>{} <b>{}</b>: {}</p>",
-                match is_up {
-                    Some(true) => "🟢",
-                    Some(false) => "🔴",
-                    None => "⚪", // ouch, we don't know if it's up or down
-                },
-                name,
-                message
+            let status = uptime_kuma_status(body);
+            let mut entry = Entry::new(
+                status,
+                vec![
+                    Span::bold(name),
+                    Span::plain(": "),
+                    Span::plain(message), // UptimeKuma not uses Markdown, but fany emojis
+                ],
             );
-            let message = HookshotMessage {
-                text: message.to_string(), // UptimeKuma not uses Markdown, but fany emojis
-                html: Some(message_html),
-                username: None,
+            // The time of the heartbeat is in UTC (its duration is only the time since the previous heartbeat)
+            let timezone = time::timezone(&self.timezone)?;
+            let beat_time = heartbeat
+                .get("time")
+                .and_then(|v| v.as_str())
+                .and_then(time::parse_utc);
+            if let Some(beat_time) = beat_time {
+                let language = self.language.unwrap_or_default();
+                let key = match status {
+                    Status::Firing => "uptime_kuma.since.down",
+                    Status::Resolved => "uptime_kuma.since.up",
+                    _ => "uptime_kuma.since.other",
+                };
+                let since = time::format_time(beat_time, Timestamp::now(), &timezone);
+                let line = language.text(key, &json!({ "since": since }));
+                entry.details.push(vec![Span::plain(&line)]);
+            }
+            let message = Message {
+                status,
+                title: Vec::new(),
+                entries: vec![entry],
+                body: None,
+                url: None,
+                language: self.language.unwrap_or_default(),
+                origin: None,
             };
-            message.submit(&self.uri).await
+            submit(&self.uri, &self.http, &self.sinks, &origin, message).await
         }
     }
 }
 
+/// Determine whether the monitor is up or down, based on the summary message of UptimeKuma
+fn uptime_kuma_status(body: &serde_json::Map<String, serde_json::Value>) -> Status {
+    let monitor_msg = body.get("msg");
+    let mut is_up = None;
+    if let Some(monitor_msg) = monitor_msg {
+        let monitor_msg = monitor_msg.as_str().unwrap_or(""); // if this is not a string, treat it as empty
+        if monitor_msg.contains("[✅ ") {
+            is_up = Some(true);
+        } else if monitor_msg.contains("[🔴 ") {
+            is_up = Some(false);
+        } else if monitor_msg.contains("Up]") {
+            // well, try that again with a little bit more fuzzy matching
+            is_up = Some(true);
+        } else if monitor_msg.contains("Down]") {
+            // well, try that again with a little bit more fuzzy matching
+            is_up = Some(false);
+        }
+    }
+
+    match is_up {
+        Some(true) => Status::Resolved,
+        Some(false) => Status::Firing,
+        None => Status::Unknown, // ouch, we don't know if it's up or down
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitlabToHookshotTransformer {
-    uri: String,
+    uri: Option<String>,        // shortcut for a single Hookshot sink
+    language: Option<Language>, // (default: En) of the generated wording, e.g. "De"
+    #[serde(default)]
+    http: HttpClientConfig,
+    sinks: Option<LinkedList<SinkConfigTypes>>,
 }
 impl GitlabToHookshotTransformer {
     async fn handle(&self, request: &HttpRequest, body: &web::Bytes) -> Result<(), String> {
         if request.method() != "POST" {
             return Err("Only POST requests are supported".to_string());
         }
+        let origin = Origin::new(request, body);
 
         let body = String::from_utf8(body.to_vec())
             .map_err(|e| "Failed to parse the body as UTF-8: ".to_string() + &e.to_string())?;
@@ -414,6 +890,15 @@ impl GitlabToHookshotTransformer {
             .as_object()
             .ok_or("The body is not a JSON object".to_string())?;
 
+        let message = self.message(body)?;
+        submit(&self.uri, &self.http, &self.sinks, &origin, message).await
+    }
+
+    /// Render the event (push, tag push, pipeline or repository update) as message
+    fn message(
+        &self,
+        body: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Message, String> {
         if let Some(object_kind) = body.get("object_kind") {
             let object_kind = object_kind
                 .as_str()
@@ -451,21 +936,12 @@ impl GitlabToHookshotTransformer {
                     let commits = commits
                         .as_array()
                         .ok_or("The commits is not an array".to_string())?;
-                    let message = format!(
-                        "{} pushed {} commit{} to {}",
-                        user,
-                        commits.len(),
-                        if commits.len() == 1 { "" } else { "s" },
-                        project_name
-                    );
-                    let mut message_html = format!(
-                        "<h3>{} pushed {} commit{} to <a href=\"{}\">{}</a></h3>",
-                        user,
-                        commits.len(),
-                        if commits.len() == 1 { "" } else { "s" },
-                        project_url,
-                        project_name
+                    let title = self.language.unwrap_or_default().spans(
+                        "gitlab.push",
+                        &json!({ "user": user, "count": commits.len() }),
+                        &[("project", Span::plain(project_name).link(project_url))],
                     );
+                    let mut entries = Vec::new();
                     for commit in commits {
                         let commit = commit
                             .as_object()
@@ -488,19 +964,41 @@ impl GitlabToHookshotTransformer {
                         let commit_message = commit_message
                             .as_str()
                             .ok_or("The message is not a string".to_string())?;
-                        message_html += &format!(
-                            "<a href=\"{}\"><code>{}</code></a> {}<br>",
-                            commit_url,
-                            commit_id[0..8].to_string(),
-                            commit_message
+                        let mut entry = Entry::new(
+                            Status::Info,
+                            vec![
+                                Span::code(commit_id.get(..8).unwrap_or(commit_id))
+                                    .link(commit_url),
+                                Span::plain(" "),
+                                Span::plain(commit_message),
+                            ],
                         );
+                        entry.author = commit
+                            .get("author")
+                            .and_then(|v| v.get("name"))
+                            .and_then(|v| v.as_str())
+                            .map(|v| v.to_string());
+                        entries.push(entry);
                     }
-                    let message = HookshotMessage {
-                        text: message,
-                        html: Some(message_html),
-                        username: None,
+                    // The comparison of all pushed commits (not available for new branches)
+                    let before = body.get("before").and_then(|v| v.as_str());
+                    let after = body.get("after").and_then(|v| v.as_str());
+                    let url = match (before, after) {
+                        (Some(before), Some(after)) if before.chars().any(|c| c != '0') => {
+                            Some(format!("{}/-/compare/{}...{}", project_url, before, after))
+                        }
+                        _ => None,
+                    };
+                    let message = Message {
+                        status: Status::Info,
+                        title,
+                        entries,
+                        body: None,
+                        url,
+                        language: self.language.unwrap_or_default(),
+                        origin: None,
                     };
-                    message.submit(&self.uri).await
+                    Ok(message)
                 }
                 "tag_push" => {
                     let project = body
@@ -527,17 +1025,20 @@ impl GitlabToHookshotTransformer {
                     let user = user
                         .as_str()
                         .ok_or("The user_name is not a string".to_string())?;
-                    let message = format!("{} pushed a tag to {}", user, project_name);
-                    let message_html = format!(
-                        "<h3>{} pushed a tag to <a href=\"{}\">{}</a></h3>",
-                        user, project_url, project_name
-                    );
-                    let message = HookshotMessage {
-                        text: message,
-                        html: Some(message_html),
-                        username: None,
+                    let message = Message {
+                        status: Status::Info,
+                        title: self.language.unwrap_or_default().spans(
+                            "gitlab.tag_push",
+                            &json!({ "user": user }),
+                            &[("project", Span::plain(project_name).link(project_url))],
+                        ),
+                        entries: Vec::new(),
+                        body: None,
+                        url: None,
+                        language: self.language.unwrap_or_default(),
+                        origin: None,
                     };
-                    message.submit(&self.uri).await
+                    Ok(message)
                 }
                 "pipeline" => {
                     let project = body
@@ -582,20 +1083,35 @@ impl GitlabToHookshotTransformer {
                     let pipeline_url = pipeline_url
                         .as_str()
                         .ok_or("The url is not a string".to_string())?;
-                    let message = format!(
-                        "Pipeline #{} {} for {}",
-                        pipeline_id, pipeline_status, project_name
-                    );
-                    let message_html = format!(
-                        "<h3>Pipeline <a href=\"{}\">#{}</a> {} for <a href=\"{}\">{}</a></h3>",
-                        pipeline_url, pipeline_id, pipeline_status, project_url, project_name
-                    );
-                    let message = HookshotMessage {
-                        text: message,
-                        html: Some(message_html),
-                        username: None,
+                    let message = Message {
+                        status: match pipeline_status {
+                            "failed" => Status::Firing,
+                            "created"
+                            | "waiting_for_resource"
+                            | "preparing"
+                            | "pending"
+                            | "running" => Status::Pending,
+                            "success" => Status::Resolved,
+                            _ => Status::Info, // e.g. canceled or skipped
+                        },
+                        title: self.language.unwrap_or_default().spans(
+                            "gitlab.pipeline",
+                            &json!({ "status": pipeline_status }),
+                            &[
+                                (
+                                    "pipeline",
+                                    Span::plain(&format!("#{}", pipeline_id)).link(pipeline_url),
+                                ),
+                                ("project", Span::plain(project_name).link(project_url)),
+                            ],
+                        ),
+                        entries: Vec::new(),
+                        body: None,
+                        url: None,
+                        language: self.language.unwrap_or_default(),
+                        origin: None,
                     };
-                    message.submit(&self.uri).await
+                    Ok(message)
                 }
                 other => Err(format!("Unsupported object_kind: {}", other)),
             }
@@ -629,17 +1145,446 @@ impl GitlabToHookshotTransformer {
                 .as_str()
                 .ok_or("The web_url is not a string".to_string())?;
 
-            let message = format!("The repository {} was updated by {}", project_name, user);
-            let message_html = format!(
-                "<h3>The repository <a href=\"{}\">{}</a> was updated by {}</h3>",
-                project_url, project_name, user
-            );
-            let message = HookshotMessage {
-                text: message,
-                html: Some(message_html),
-                username: None,
+            let message = Message {
+                status: Status::Info,
+                title: self.language.unwrap_or_default().spans(
+                    "gitlab.repository_update",
+                    &json!({ "user": user }),
+                    &[("project", Span::plain(project_name).link(project_url))],
+                ),
+                entries: Vec::new(),
+                body: None,
+                url: None,
+                language: self.language.unwrap_or_default(),
+                origin: None,
             };
-            message.submit(&self.uri).await
+            Ok(message)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::spans_to_text;
+
+    /// Parse the transformer (or payload) from JSON, panicking on errors
+    fn parse<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn headlines(message: &Message) -> Vec<String> {
+        message
+            .entries
+            .iter()
+            .map(|entry| spans_to_text(&entry.headline))
+            .collect()
+    }
+
+    #[test]
+    fn gitlab_push_shortens_the_commit_ids() {
+        let transformer: GitlabToHookshotTransformer = parse(json!({}));
+        let commit =
+            |id: &str| json!({ "id": id, "url": "https://gitlab.example.com/c", "message": "Fix" });
+        let message = transformer
+            .message(&parse(json!({
+                "object_kind": "push",
+                "user_name": "Jane",
+                "before": "0000000000000000000000000000000000000000",
+                "after": "b6568db1bc1dcd7f8b4d5a946b0b91f9dacd7327",
+                "project": { "name": "app", "web_url": "https://gitlab.example.com/app" },
+                "commits": [
+                    commit("b6568db1bc1dcd7f8b4d5a946b0b91f9dacd7327"),
+                    commit("01"),
+                    commit("€€€€"), // no character boundary at byte 8
+                ],
+            })))
+            .unwrap();
+        assert_eq!(
+            headlines(&message),
+            vec!["b6568db1 Fix", "01 Fix", "€€€€ Fix"]
+        );
+        assert_eq!(message.url, None); // new branch, nothing to compare
+        assert_eq!(
+            spans_to_text(&message.title),
+            "Jane pushed 3 commits to app"
+        );
+    }
+
+    /// An alert of Grafana's unified alerting, as sent by its webhook contact point
+    fn grafana_alert(status: &str, labels: serde_json::Value) -> serde_json::Value {
+        json!({
+            "status": status,
+            "labels": labels,
+            "annotations": { "summary": "CPU usage is high" },
+            "startsAt": "2024-05-01T10:00:00Z",
+            "endsAt": "0001-01-01T00:00:00Z",
+            "generatorURL": "https://grafana.example.com/alerting/grafana/abc/view",
+            "fingerprint": format!("fp-{}", labels["instance"].as_str().unwrap_or("none")),
+            "silenceURL": "https://grafana.example.com/alerting/silence/new",
+            "dashboardURL": "",
+            "panelURL": "",
+            "values": { "B": 97.3121, "C": 1 },
+            "valueString": "[ var='B' labels={instance=db1} value=97.3121 ], [ var='C' labels={instance=db1} value=1 ]",
+        })
+    }
+
+    /// Render a webhook payload with the alerts, using the transformer configuration
+    fn grafana(config: serde_json::Value, alerts: Vec<serde_json::Value>) -> Message {
+        let transformer: GrafanaToHookshotTransformer = parse(config);
+        transformer
+            .message(&parse(json!({
+                "receiver": "webhook",
+                "status": "firing",
+                "orgId": 1,
+                "alerts": alerts,
+                "externalURL": "https://grafana.example.com/",
+                "version": "1",
+                "groupKey": "{}:{}",
+                "title": "[FIRING:1] HighCPU",
+            })))
+            .unwrap()
+    }
+
+    #[test]
+    fn grafana_values_are_parsed() {
+        let alert: serde_json::Map<String, serde_json::Value> =
+            parse(grafana_alert("firing", json!({ "alertname": "HighCPU" })));
+        let expected = vec![("B".to_string(), 97.3121), ("C".to_string(), 1.0)];
+        assert_eq!(grafana_values(&alert), expected);
+
+        // Older versions only send the value string
+        let mut older = alert.clone();
+        older.remove("values");
+        assert_eq!(grafana_values(&older), expected);
+        older.insert("values".to_string(), json!({}));
+        assert_eq!(grafana_values(&older), expected);
+        older.insert(
+            "valueString".to_string(),
+            json!("[ var='A' labels={} value=NaN ], [ var='B' labels={host=a b} value=-1.5e3 ], [ var='C' value=x ], [ var='D"),
+        );
+        assert_eq!(grafana_values(&older), vec![("B".to_string(), -1500.0)]);
+        older.remove("valueString");
+        assert_eq!(grafana_values(&older), Vec::new());
+    }
+
+    #[test]
+    fn grafana_values_are_rendered() {
+        let values = vec![("B".to_string(), 97.3121), ("C".to_string(), 1.0)];
+        let text = |config: serde_json::Value| {
+            let values_config: GrafanaValues = parse(config);
+            spans_to_text(&values_config.spans(&values))
+        };
+        assert_eq!(text(json!({})), " · B 97.31, C 1");
+        assert_eq!(text(json!({ "precision": 0 })), " · B 97, C 1");
+        assert_eq!(text(json!({ "precision": 3 })), " · B 97.312, C 1");
+        assert_eq!(
+            text(json!({ "refs": { "B": { "name": "CPU", "unit": "%" } } })),
+            " · CPU 97.31%"
+        );
+        assert_eq!(
+            text(json!({ "refs": { "C": {}, "X": { "name": "unknown" } } })),
+            " · C 1"
+        );
+        assert_eq!(text(json!({ "refs": {} })), "");
+        let spans = parse::<GrafanaValues>(json!({})).spans(&values);
+        assert_eq!(spans[2].style, crate::message::Style::Bold); // the value itself
+    }
+
+    #[test]
+    fn grafana_values_are_shown_in_the_headline() {
+        let labels = json!({ "alertname": "HighCPU", "instance": "db1" });
+        let config = json!({ "values": { "precision": 1, "refs": { "B": { "name": "CPU", "unit": "%" } } } });
+        let message = grafana(config, vec![grafana_alert("firing", labels)]);
+        assert_eq!(
+            headlines(&message),
+            vec!["HighCPU at db1: CPU usage is high · CPU 97.3%"]
+        );
+    }
+
+    #[test]
+    fn grafana_alerts_are_grouped() {
+        let alert = |status: &str, instance: &str, namespace: Option<&str>, severity: &str| {
+            let mut labels =
+                json!({ "alertname": "HighCPU", "instance": instance, "severity": severity });
+            if let Some(namespace) = namespace {
+                labels["namespace"] = namespace.into();
+            }
+            grafana_alert(status, labels)
+        };
+        let message = grafana(
+            json!({ "group_by": "namespace" }),
+            vec![
+                alert("firing", "a", Some("web"), "warning"),
+                alert("firing", "b", None, "critical"),
+                alert("firing", "c", Some("db"), "critical"),
+                alert("resolved", "d", Some("web"), "info"),
+                alert("alerting", "e", Some("web"), "warning"),
+                alert("firing", "f", Some("cache"), "unknown"),
+            ],
+        );
+        let groups: Vec<(String, String)> = message
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.group.clone().unwrap(),
+                    entry.labels["instance"].clone(),
+                )
+            })
+            .collect();
+        let group = |heading: &str, instance: &str| (heading.to_string(), instance.to_string());
+        assert_eq!(
+            groups,
+            vec![
+                group("namespace=db (1 firing)", "c"),
+                group("namespace=web (1 firing, 1 pending, 1 resolved)", "a"),
+                group("namespace=web (1 firing, 1 pending, 1 resolved)", "d"),
+                group("namespace=web (1 firing, 1 pending, 1 resolved)", "e"),
+                group("namespace=cache (1 firing)", "f"),
+                // Even though its alert is critical
+                group("Without namespace (1 firing)", "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn grafana_group_headings_are_localized() {
+        let alerts = vec![grafana_alert("resolved", json!({ "alertname": "HighCPU" }))];
+        let message = grafana(json!({ "group_by": "team", "language": "De" }), alerts);
+        assert_eq!(
+            message.entries[0].group.as_deref(),
+            Some("Ohne team (1 behoben)")
+        );
+    }
+
+    /// An outgoing webhook of Grafana OnCall
+    fn oncall(event: &str, state: &str, user: Option<&str>) -> serde_json::Value {
+        json!({
+            "event": { "type": event, "time": "2024-05-01T10:05:00.000000Z" },
+            "user": user.map(|username| json!({ "id": "UABC", "username": username })),
+            "alert_group": {
+                "id": "I68T24C13IFW1",
+                "integration_id": "CFRPV98RPR1U8",
+                "alerts_count": 3,
+                "state": state,
+                "created_at": "2024-05-01T10:00:00Z",
+                "title": "HighCPU",
+                "permalinks": {
+                    "slack": null,
+                    "web": "https://oncall.example.com/alert-groups/I68T24C13IFW1",
+                },
+            },
+            "alert_group_id": "I68T24C13IFW1",
+            "integration": { "id": "CFRPV98RPR1U8", "type": "grafana_alerting", "name": "Grafana Alerting" },
+        })
+    }
+
+    fn details(message: &Message) -> Vec<String> {
+        message.entries[0]
+            .details
+            .iter()
+            .map(|detail| spans_to_text(detail))
+            .collect()
+    }
+
+    #[test]
+    fn grafana_oncall_events_are_rendered() {
+        let transformer: GrafanaToHookshotTransformer = parse(json!({}));
+        let message = transformer
+            .message(&parse(oncall("acknowledge", "acknowledged", Some("jane"))))
+            .unwrap();
+        assert_eq!(message.status, Status::Pending);
+        assert_eq!(headlines(&message), vec!["HighCPU is acknowledged"]);
+        assert_eq!(details(&message), vec!["Acknowledged by jane (3 alerts)"]);
+        let entry = &message.entries[0];
+        assert_eq!(entry.fingerprint.as_deref(), Some("I68T24C13IFW1"));
+        assert_eq!(entry.labels["integration"], "Grafana Alerting");
+        assert_eq!(entry.links[0].label, "alert group");
+        assert_eq!(
+            message.url.as_deref(),
+            Some("https://oncall.example.com/alert-groups/I68T24C13IFW1")
+        );
+
+        for (event, state, status, detail) in [
+            (
+                "escalation",
+                "firing",
+                Status::Firing,
+                "Escalated (3 alerts)",
+            ),
+            ("escalation", "new", Status::Firing, "Escalated (3 alerts)"),
+            (
+                "resolve",
+                "resolved",
+                Status::Resolved,
+                "Resolved (3 alerts)",
+            ),
+            ("silence", "silenced", Status::Info, "Silenced (3 alerts)"),
+            (
+                "personal_notification",
+                "firing",
+                Status::Firing,
+                "Event personal_notification (3 alerts)",
+            ),
+        ] {
+            let message = transformer
+                .message(&parse(oncall(event, state, None)))
+                .unwrap();
+            assert_eq!(message.status, status, "{}", event);
+            assert_eq!(details(&message), vec![detail]);
+        }
+        let message = transformer
+            .message(&parse(oncall("escalation", "unknown_state", None)))
+            .unwrap();
+        assert_eq!(message.status, Status::Unknown);
+        assert_eq!(headlines(&message), vec!["HighCPU is unknown_state"]);
+    }
+
+    #[test]
+    fn grafana_oncall_alert_counts_are_pluralized() {
+        let transformer: GrafanaToHookshotTransformer = parse(json!({ "language": "De" }));
+        let mut body = oncall("resolve", "resolved", Some("jane"));
+        body["alert_group"]["alerts_count"] = 1.into();
+        body["alert_group"]["title"] = serde_json::Value::Null;
+        let message = transformer.message(&parse(body)).unwrap();
+        assert_eq!(headlines(&message), vec!["Alarmgruppe ist behoben"]);
+        assert_eq!(details(&message), vec!["Behoben von jane (1 Alarm)"]);
+    }
+
+    /// A notification of the legacy alerting (before Grafana 9)
+    fn legacy(state: &str) -> serde_json::Value {
+        json!({
+            "dashboardId": 1,
+            "evalMatches": [
+                { "value": 100, "metric": "High value", "tags": null },
+                { "value": 200.456, "metric": "Higher value", "tags": null },
+            ],
+            "imageUrl": "https://grafana.example.com/render/1.png",
+            "message": "Notification message",
+            "orgId": 1,
+            "panelId": 2,
+            "ruleId": 7,
+            "ruleName": "Panel Title alert",
+            "ruleUrl": "https://grafana.example.com/d/hZ7BuVbWz/dashboard?panelId=2",
+            "state": state,
+            "tags": { "team": "ops" },
+            "title": "[Alerting] Panel Title alert",
+        })
+    }
+
+    #[test]
+    fn grafana_legacy_alerts_are_rendered() {
+        let transformer: GrafanaToHookshotTransformer = parse(json!({ "show_labels": ["team"] }));
+        let message = transformer.message(&parse(legacy("alerting"))).unwrap();
+        assert_eq!(message.status, Status::Firing);
+        assert_eq!(
+            headlines(&message),
+            vec!["Panel Title alert: Notification message · High value 100, Higher value 200.46"]
+        );
+        assert_eq!(details(&message), vec!["team=ops"]);
+        let entry = &message.entries[0];
+        assert_eq!(entry.fingerprint.as_deref(), Some("7"));
+        let links: Vec<&str> = entry.links.iter().map(|link| link.label.as_str()).collect();
+        assert_eq!(links, vec!["rule", "image"]);
+        assert_eq!(
+            entry.image.as_deref(),
+            Some("https://grafana.example.com/render/1.png")
+        );
+        assert_eq!(
+            message.url.as_deref(),
+            Some("https://grafana.example.com/d/hZ7BuVbWz/dashboard?panelId=2")
+        );
+
+        for (state, status) in [
+            ("ok", Status::Resolved),
+            ("pending", Status::Pending),
+            ("paused", Status::Info),
+            ("no_data", Status::Unknown),
+        ] {
+            let message = transformer.message(&parse(legacy(state))).unwrap();
+            assert_eq!(message.status, status, "{}", state);
+        }
+
+        // Without a rule id, the name identifies the alert
+        let mut body = legacy("ok");
+        body.as_object_mut().unwrap().remove("ruleId");
+        let message = transformer.message(&parse(body)).unwrap();
+        assert_eq!(
+            message.entries[0].fingerprint.as_deref(),
+            Some("Panel Title alert")
+        );
+    }
+
+    #[test]
+    fn grafana_legacy_messages_can_just_be_shown() {
+        let transformer: GrafanaToHookshotTransformer = parse(json!({ "just_show_message": true }));
+        let message = transformer.message(&parse(legacy("alerting"))).unwrap();
+        assert_eq!(message.status, Status::Firing);
+        assert_eq!(message.body.as_deref(), Some("Notification message"));
+        let message = transformer.message(&parse(legacy("ok"))).unwrap();
+        assert_eq!(message.status, Status::Resolved);
+    }
+
+    fn labeled_alert() -> serde_json::Value {
+        grafana_alert(
+            "firing",
+            json!({
+                "alertname": "HighCPU",
+                "instance": "db1",
+                "severity": "critical",
+                "team": "ops",
+                "grafana_folder": "Infrastructure",
+                "__alert_rule_uid__": "a1b2c3",
+            }),
+        )
+    }
+
+    #[test]
+    fn grafana_labels_are_shown() {
+        let shown = |config: serde_json::Value| {
+            let message = grafana(config, vec![labeled_alert()]);
+            details(&message)
+                .into_iter()
+                .filter(|detail| detail.contains('='))
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(shown(json!({})), Vec::<String>::new());
+        assert_eq!(
+            shown(json!({ "show_labels": ["team", "missing", "severity"] })),
+            vec!["team=ops, severity=critical"]
+        );
+        // All labels, except those of the headline and the hidden ones
+        assert_eq!(
+            shown(json!({ "show_labels": ["*"] })),
+            vec!["severity=critical, team=ops"]
+        );
+        assert_eq!(
+            shown(json!({ "show_labels": ["*"], "hide_labels": ["team"] })),
+            vec!["__alert_rule_uid__=a1b2c3, grafana_folder=Infrastructure, severity=critical"]
+        );
+        // Hidden labels cannot be shown explicitly either
+        assert_eq!(
+            shown(json!({ "show_labels": ["grafana_folder"] })),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn grafana_hidden_labels_do_not_reach_the_sinks() {
+        let message = grafana(json!({}), vec![labeled_alert()]);
+        let labels: Vec<&String> = message.entries[0].labels.keys().collect();
+        assert_eq!(labels, vec!["alertname", "instance", "severity", "team"]);
+        let event = crate::template::event(&message);
+        assert_eq!(event["labels"]["team"], "ops");
+        assert_eq!(event["labels"].get("grafana_folder"), None);
+        assert_eq!(event.get("__alert_rule_uid__"), None);
+        assert_eq!(event["entries"][0]["labels"].get("grafana_folder"), None);
+
+        let message = grafana(json!({ "hide_labels": [] }), vec![labeled_alert()]);
+        assert_eq!(
+            message.entries[0].labels["grafana_folder"],
+            "Infrastructure"
+        );
+    }
+}